@@ -0,0 +1,25 @@
+CREATE TABLE users(
+    id INTEGER NOT NULL PRIMARY KEY,
+
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    role TEXT CHECK(role IN ('viewer', 'operator', 'admin')) NOT NULL
+);
+
+CREATE TABLE sessions(
+    id INTEGER NOT NULL PRIMARY KEY,
+
+    token_hash TEXT NOT NULL UNIQUE,
+    user_id INTEGER NOT NULL,
+    expires BIGINT NOT NULL,
+
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE api_tokens(
+    id INTEGER NOT NULL PRIMARY KEY,
+
+    name TEXT NOT NULL UNIQUE,
+    token_hash TEXT NOT NULL UNIQUE,
+    role TEXT CHECK(role IN ('viewer', 'operator', 'admin')) NOT NULL
+);
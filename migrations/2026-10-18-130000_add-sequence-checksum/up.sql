@@ -0,0 +1 @@
+ALTER TABLE sequences ADD COLUMN checksum TEXT NOT NULL DEFAULT '';
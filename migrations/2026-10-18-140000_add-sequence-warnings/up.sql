@@ -0,0 +1,8 @@
+CREATE TABLE warnings(
+    id INTEGER NOT NULL PRIMARY KEY,
+
+    sequence_id INTEGER NOT NULL,
+    message TEXT NOT NULL,
+
+    FOREIGN KEY (sequence_id) REFERENCES sequences(id)
+);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS `tags`;
+ALTER TABLE sequences DROP COLUMN uploaded;
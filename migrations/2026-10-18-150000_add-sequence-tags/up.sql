@@ -0,0 +1,9 @@
+ALTER TABLE sequences ADD COLUMN uploaded BIGINT NOT NULL DEFAULT 0;
+
+CREATE TABLE tags(
+    sequence_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+
+    PRIMARY KEY(sequence_id, name),
+    FOREIGN KEY (sequence_id) REFERENCES sequences(id)
+);
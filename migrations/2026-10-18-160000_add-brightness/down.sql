@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS `settings`;
+ALTER TABLE sequences DROP COLUMN brightness;
+ALTER TABLE playlists_sequences DROP COLUMN brightness;
@@ -0,0 +1,7 @@
+CREATE TABLE settings(
+    name TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+
+ALTER TABLE sequences ADD COLUMN brightness INTEGER NOT NULL DEFAULT 100;
+ALTER TABLE playlists_sequences ADD COLUMN brightness INTEGER NOT NULL DEFAULT 100;
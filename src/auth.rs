@@ -0,0 +1,335 @@
+use std::net::IpAddr;
+
+use anyhow::{anyhow, Result};
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use base64::Engine;
+use diesel::SqliteConnection;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::{AuthConfig, Config},
+    db::{
+        self,
+        models::{NewUser, Role},
+    },
+    storage,
+};
+
+/// Name of the cookie holding the session token
+pub const SESSION_COOKIE: &str = "ledplayr_session";
+
+/// Who made a request
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub name: String,
+    pub role: Role,
+}
+
+pub fn enabled(cfg: &Config) -> bool {
+    cfg.auth.as_ref().and_then(|a| a.enabled).unwrap_or(false)
+}
+
+pub fn session_secs(cfg: &Config) -> i64 {
+    let hours = cfg
+        .auth
+        .as_ref()
+        .and_then(|a| a.session_hours)
+        .unwrap_or(24 * 7);
+
+    (hours * 60 * 60) as i64
+}
+
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| anyhow!("Could not hash password: {e}"))
+}
+
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(h) => Argon2::default()
+            .verify_password(password.as_bytes(), &h)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Generate a random token for a session or API client
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+
+    storage::hex(&bytes)
+}
+
+/// Tokens are only stored hashed so a leaked database can't be
+/// used to log in
+pub fn hash_token(token: &str) -> String {
+    storage::hex(&Sha256::digest(token.as_bytes()))
+}
+
+/// Create the admin user from the config if there are no users yet
+///
+/// If no password is configured one is generated and returned with
+/// the username. It's never logged, logs can be read through the API.
+pub fn init(cfg: &Config, conn: &mut SqliteConnection) -> Result<Option<(String, String)>> {
+    if !enabled(cfg) || db::count_users(conn)? > 0 {
+        return Ok(None);
+    }
+
+    let auth = cfg.auth.as_ref();
+    let username = auth
+        .and_then(|a| a.admin_username.clone())
+        .unwrap_or("admin".into());
+    let (password, generated) = match auth.and_then(|a| a.admin_password.clone()) {
+        Some(p) => (p, false),
+        None => {
+            tracing::warn!("Created user '{username}' with a generated password, please change it");
+            (generate_token()[..16].to_string(), true)
+        }
+    };
+
+    db::new_user(
+        conn,
+        NewUser {
+            username: username.clone(),
+            password_hash: hash_password(&password)?,
+            role: Role::Admin,
+        },
+    )?;
+
+    Ok(generated.then_some((username, password)))
+}
+
+/// Work out who sent a request from its `Authorization` and
+/// `Cookie` headers
+///
+/// API tokens can be sent as a bearer token or as the password of
+/// basic auth, which is what xLights uses. Basic auth also accepts
+/// a username and password.
+pub fn authenticate(
+    conn: &mut SqliteConnection,
+    authorization: Option<&str>,
+    cookie: Option<&str>,
+) -> Result<Option<Principal>> {
+    if let Some(authorization) = authorization {
+        if let Some(token) = authorization.strip_prefix("Bearer ") {
+            return token_principal(conn, token.trim());
+        }
+
+        if let Some(basic) = authorization.strip_prefix("Basic ") {
+            let decoded = base64::engine::general_purpose::STANDARD
+                .decode(basic.trim())
+                .ok()
+                .and_then(|d| String::from_utf8(d).ok());
+            let Some((username, password)) = decoded.as_ref().and_then(|d| d.split_once(':'))
+            else {
+                return Ok(None);
+            };
+
+            if let Some(p) = token_principal(conn, password)? {
+                return Ok(Some(p));
+            }
+
+            return Ok(db::get_user(conn, username)?
+                .filter(|u| verify_password(password, &u.password_hash))
+                .map(|u| Principal {
+                    name: u.username,
+                    role: u.role,
+                }));
+        }
+    }
+
+    if let Some(token) = cookie.and_then(session_token) {
+        return Ok(
+            db::get_session_user(conn, &hash_token(token))?.map(|u| Principal {
+                name: u.username,
+                role: u.role,
+            }),
+        );
+    }
+
+    Ok(None)
+}
+
+fn token_principal(conn: &mut SqliteConnection, token: &str) -> Result<Option<Principal>> {
+    Ok(db::get_token(conn, &hash_token(token))?.map(|t| Principal {
+        name: t.name,
+        role: t.role,
+    }))
+}
+
+/// Find the session token in a `Cookie` header
+pub fn session_token(cookie: &str) -> Option<&str> {
+    cookie
+        .split(';')
+        .filter_map(|c| c.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value)
+}
+
+/// The role needed to use a route, or `None` if anyone can use it
+pub fn required_role(method: &str, path: &str) -> Option<Role> {
+    match (method, path) {
+        (_, "/api/auth/login" | "/api/auth/logout") => None,
+        (_, p) if p.starts_with("/rapidoc") || p.starts_with("/api-docs") => None,
+
+        (_, "/api/users" | "/api/user" | "/api/user/{username}") => Some(Role::Admin),
+        (_, "/api/tokens" | "/api/token" | "/api/token/{name}") => Some(Role::Admin),
+        (_, "/api/logs" | "/api/log/{name}") => Some(Role::Admin),
+
+        (
+            "GET",
+            "/api/player/schedule"
+            | "/api/player/bridge"
+            | "/api/player/stop"
+            | "/api/playlists/stop"
+            | "/api/playlist/{playlist}/start"
+            | "/api/command/{command}"
+            | "/api/command/{command}/{*args}",
+        ) => Some(Role::Operator),
+        ("GET", _) => Some(Role::Viewer),
+        ("POST", "/api/test_pattern") => Some(Role::Viewer),
+
+        (
+            _,
+            "/api/command"
+            | "/api/player/test"
+            | "/api/player/record"
+            | "/api/overrides"
+            | "/api/override/{model}"
+            | "/api/player/brightness"
+            | "/api/playlist"
+            | "/api/playlist/{playlist}"
+            | "/api/schedule"
+            | "/api/schedule/{schedule}"
+            | "/api/scene"
+            | "/api/scenes/{scene}",
+        ) => Some(Role::Operator),
+
+        _ => Some(Role::Admin),
+    }
+}
+
+/// Whether a route and method is one xLights and other FPP clients use
+pub fn is_fpp_compatible(method: &str, path: &str) -> bool {
+    matches!(
+        (method, path),
+        ("POST", "/jqupload.php" | "/api/upload" | "/api/command")
+            | (
+                "GET",
+                "/fppxml.php"
+                    | "/api/command/{command}"
+                    | "/api/command/{command}/{*args}"
+                    | "/api/fppd/status"
+                    | "/api/playlists"
+                    | "/api/playlists/stop"
+                    | "/api/playlist/{playlist}/start"
+                    | "/api/sequence/{filename}"
+                    | "/api/system/info"
+            )
+            | (
+                "GET" | "POST",
+                "/api/models"
+                    | "/api/configfile/virtualdisplaymap"
+                    | "/api/channel/output/universeOutputs"
+            )
+    )
+}
+
+/// Whether the address is allowed to use the FPP compatible routes
+/// without logging in
+pub fn fpp_allowed(auth: Option<&AuthConfig>, ip: IpAddr) -> bool {
+    let Some(allow) = auth.and_then(|a| a.fpp_allow.as_ref()) else {
+        return false;
+    };
+
+    allow.iter().any(|entry| {
+        let (net, bits) = match entry.split_once('/') {
+            Some((net, bits)) => (net, bits.parse::<u32>().ok()),
+            None => (entry.as_str(), None),
+        };
+
+        match (net.parse::<IpAddr>(), ip) {
+            (Ok(IpAddr::V4(net)), IpAddr::V4(ip)) => {
+                let bits = bits.unwrap_or(32).min(32);
+                let mask = u32::MAX.checked_shl(32 - bits).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (Ok(IpAddr::V6(net)), IpAddr::V6(ip)) => {
+                let bits = bits.unwrap_or(128).min(128);
+                let mask = u128::MAX.checked_shl(128 - bits).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, sync::Arc};
+
+    use parking_lot::Mutex;
+
+    use super::*;
+
+    /// Collects everything logged
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn generated_password_isnt_logged() {
+        let cfg: Config =
+            toml::from_str("database_url = \":memory:\"\nstorage = \"\"\n[auth]\nenabled = true")
+                .unwrap();
+        let mut conn = db::get(&cfg).unwrap();
+        db::run_migrations(&mut conn).unwrap();
+
+        let logs = Logs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer({
+                let logs = logs.clone();
+                move || logs.clone()
+            })
+            .with_ansi(false)
+            .finish();
+        let created = tracing::subscriber::with_default(subscriber, || init(&cfg, &mut conn));
+
+        let (username, password) = created.unwrap().unwrap();
+        let logs = String::from_utf8(logs.0.lock().clone()).unwrap();
+        assert!(logs.contains("Created user 'admin'"), "nothing was logged");
+        assert!(!logs.contains(&password), "password was logged: {logs}");
+
+        let user = db::get_user(&mut conn, &username).unwrap().unwrap();
+        assert!(verify_password(&password, &user.password_hash));
+
+        // Only generated the first time
+        assert!(init(&cfg, &mut conn).unwrap().is_none());
+    }
+
+    #[test]
+    fn logs_are_admin_only() {
+        for path in ["/api/logs", "/api/log/{name}"] {
+            assert_eq!(required_role("GET", path), Some(Role::Admin), "{path}");
+        }
+    }
+}
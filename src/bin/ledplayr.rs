@@ -4,11 +4,11 @@ use anyhow::{anyhow, Context, Result};
 
 use dotenvy::dotenv;
 use ledplayr::{
-    built_info, button, config::Config, db, error::AppError, fpp, models::PlayerStatus, player,
-    state::State, storage, web::router,
+    auth, built_info, button, config::Config, db, dimmer, error::AppError, fpp, models::PlayerInfo,
+    overlay, player, state::State, storage, web::router,
 };
 use parking_lot::Mutex;
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc, watch};
 use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use tracing::level_filters::LevelFilter;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
@@ -76,6 +76,17 @@ async fn main() -> Result<()> {
     tracing::info!("Setting up database");
     let mut db_conn = db::get(&cfg)?;
     db::run_migrations(&mut db_conn)?;
+    if let Some((username, password)) = auth::init(&cfg, &mut db_conn)? {
+        // Only ever printed here, the logs can be read through the API
+        eprintln!("Password for '{username}' is '{password}'");
+    }
+
+    let brightness = db::get_setting(&mut db_conn, dimmer::MASTER_SETTING)?
+        .and_then(|b| b.parse::<u8>().ok())
+        .unwrap_or(100);
+    let overrides = db::get_setting(&mut db_conn, overlay::SETTING)?
+        .and_then(|o| serde_json::from_str(&o).ok())
+        .unwrap_or_default();
 
     tracing::info!("Configuring storage");
     storage::init(&cfg)?;
@@ -95,11 +106,14 @@ async fn main() -> Result<()> {
         cfg,
         db_conn,
         player_ctrl: player_ctrl_tx,
-        player_status: PlayerStatus::Stopped,
+        player: watch::Sender::new(PlayerInfo::default()),
+        frames: broadcast::Sender::new(16),
+        brightness: watch::Sender::new(brightness),
+        overrides: watch::Sender::new(overrides),
     }));
 
     if multicast_enabled {
-        tracker.spawn(fpp::listen(cancel.clone()));
+        tracker.spawn(fpp::listen(state.clone(), cancel.clone()));
     }
     tracker.spawn(button::listen(state.clone(), cancel.clone()));
     tracker.spawn(router::run_server(state.clone(), cancel.clone()));
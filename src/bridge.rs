@@ -0,0 +1,387 @@
+use std::{
+    net::{Ipv4Addr, SocketAddrV4},
+    ops::Range,
+};
+
+use anyhow::{Context, Result};
+use ddp_rs::protocol;
+use tokio::{
+    net::UdpSocket,
+    sync::mpsc::Sender,
+    time::{self, Duration, Instant},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{config::BridgeConfig, player::Data};
+
+/// Port xLights sends DDP to
+pub const DDP_PORT: u16 = 4048;
+/// Port E1.31 is sent to
+pub const E131_PORT: u16 = 5568;
+/// Channels in each E1.31 universe, the xLights default
+pub const E131_UNIVERSE_SIZE: u32 = 510;
+
+/// Length of the E1.31 headers before the DMX start code
+const E131_HEADER_LEN: usize = 125;
+/// Highest E1.31 universe which has a multicast group
+const E131_MAX_UNIVERSE: usize = 63999;
+
+/// How long to wait for the rest of a frame before sending what has
+/// arrived, for senders which never mark the end of a frame
+const FRAME_TIMEOUT: Duration = Duration::from_millis(25);
+
+/*
+* DDP: http://www.3waylabs.com/ddp/
+* E1.31: https://tsp.esta.org/tsp/documents/docs/ANSI_E1-31-2018.pdf
+*/
+
+/// Listen for DDP and E1.31 until cancelled, passing each complete
+/// frame of the first `channels` channels on to `out`
+///
+/// The multicast groups of the E1.31 universes covering `channels` are
+/// joined once when listening starts.
+///
+/// DDP is buffered until a packet with the PUSH flag. E1.31 is buffered
+/// until the highest universe seen so far arrives, or a universe which
+/// is already in the frame arrives again. Either way a frame is sent
+/// anyway if it isn't finished within `FRAME_TIMEOUT`.
+pub async fn listen(
+    cfg: Option<&BridgeConfig>,
+    channels: usize,
+    out: Sender<Data>,
+    cancel: CancellationToken,
+) -> Result<()> {
+    let ddp_port = cfg.and_then(|c| c.ddp_port).unwrap_or(DDP_PORT);
+    let e131_port = cfg.and_then(|c| c.e131_port).unwrap_or(E131_PORT);
+    let universe_size = cfg
+        .and_then(|c| c.e131_universe_size)
+        .unwrap_or(E131_UNIVERSE_SIZE) as usize;
+
+    let ddp = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, ddp_port))
+        .await
+        .with_context(|| format!("Failed to listen for DDP on port {ddp_port}"))?;
+    let e131 = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, e131_port))
+        .await
+        .with_context(|| format!("Failed to listen for E1.31 on port {e131_port}"))?;
+
+    let universes = channels.div_ceil(universe_size).min(E131_MAX_UNIVERSE);
+    for universe in 1..=universes {
+        if let Err(e) = e131.join_multicast_v4(multicast_group(universe), Ipv4Addr::UNSPECIFIED) {
+            tracing::warn!(
+                "Couldn't join E1.31 multicast for universes {universe} to {universes}, \
+                 send them unicast instead: {e}"
+            );
+            break;
+        }
+    }
+
+    tracing::info!("Bridging DDP on port {ddp_port} and E1.31 on port {e131_port}");
+
+    let mut ddp_frame = Frame::new(channels);
+    let mut e131_frame = E131Frame::new(channels);
+    let mut ddp_buf = [0; 1500];
+    let mut e131_buf = [0; 1500];
+    loop {
+        let deadline = [ddp_frame.deadline(), e131_frame.frame.deadline()]
+            .into_iter()
+            .flatten()
+            .min();
+
+        let mut ready = Vec::new();
+        tokio::select! {
+            _ = cancel.cancelled() => return Ok(()),
+            _ = time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                let now = Instant::now();
+                for frame in [&mut ddp_frame, &mut e131_frame.frame] {
+                    if frame.deadline().is_some_and(|d| d <= now) {
+                        ready.extend(frame.take());
+                    }
+                }
+            }
+            res = ddp.recv_from(&mut ddp_buf) => {
+                let (len, _) = res?;
+                if let Some((data, push)) = parse_ddp(&ddp_buf[..len]) {
+                    ddp_frame.add(data);
+                    if push {
+                        ready.extend(ddp_frame.take());
+                    }
+                }
+            }
+            res = e131.recv_from(&mut e131_buf) => {
+                let (len, _) = res?;
+                if let Some((universe, data)) = parse_e131(&e131_buf[..len], universe_size) {
+                    e131_frame.add(universe, data, &mut ready);
+                }
+            }
+        };
+
+        for data in ready {
+            if out.send(data).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// The multicast group E1.31 for `universe` is sent to
+fn multicast_group(universe: usize) -> Ipv4Addr {
+    Ipv4Addr::new(239, 255, (universe >> 8) as u8, universe as u8)
+}
+
+/// Channels received since the last frame was sent
+struct Frame {
+    channels: Vec<u8>,
+    /// Channels changed since the last frame was sent
+    changed: Option<Range<usize>>,
+    /// When the first of those changes arrived
+    started: Option<Instant>,
+}
+
+impl Frame {
+    fn new(channels: usize) -> Self {
+        Self {
+            channels: vec![0; channels],
+            changed: None,
+            started: None,
+        }
+    }
+
+    /// Store `data`, dropping any channels past the end of the frame
+    fn add(&mut self, data: Data) {
+        let start = data.offset.min(self.channels.len());
+        let end = data
+            .offset
+            .saturating_add(data.data.len())
+            .min(self.channels.len());
+        if start == end {
+            return;
+        }
+
+        self.channels[start..end].copy_from_slice(&data.data[..end - start]);
+        self.changed = Some(match self.changed.take() {
+            Some(r) => r.start.min(start)..r.end.max(end),
+            None => start..end,
+        });
+        self.started.get_or_insert_with(Instant::now);
+    }
+
+    /// The changed channels, if there are any
+    ///
+    /// Channels between changes which weren't themselves changed keep
+    /// the values they were last sent with.
+    fn take(&mut self) -> Option<Data> {
+        self.started = None;
+        let changed = self.changed.take()?;
+        Some(Data {
+            offset: changed.start,
+            data: self.channels[changed].to_vec(),
+        })
+    }
+
+    /// When to give up waiting for the rest of the frame
+    fn deadline(&self) -> Option<Instant> {
+        self.started.map(|s| s + FRAME_TIMEOUT)
+    }
+}
+
+/// Universes are sent one after another with nothing marking the end
+/// of a frame, so a frame ends when a universe repeats or the highest
+/// universe seen so far arrives
+struct E131Frame {
+    frame: Frame,
+    /// Universes received in this frame
+    seen: Vec<usize>,
+    /// The highest universe in the previous frames
+    last: Option<usize>,
+}
+
+impl E131Frame {
+    fn new(channels: usize) -> Self {
+        Self {
+            frame: Frame::new(channels),
+            seen: Vec::new(),
+            last: None,
+        }
+    }
+
+    /// Store the data for `universe`, adding any frames it completes
+    /// to `ready`
+    fn add(&mut self, universe: usize, data: Data, ready: &mut Vec<Data>) {
+        if self.seen.contains(&universe) {
+            self.last = self.seen.iter().max().copied();
+            self.seen.clear();
+            ready.extend(self.frame.take());
+        } else if self.last.is_some_and(|l| universe > l) {
+            // More universes are being sent than before
+            self.last = Some(universe);
+        }
+
+        self.frame.add(data);
+        self.seen.push(universe);
+
+        if self.last == Some(universe) {
+            self.seen.clear();
+            ready.extend(self.frame.take());
+        }
+    }
+}
+
+/// Get the channel data from a DDP packet
+///
+/// Only data for the default output device is used, queries, replies
+/// and control messages are ignored. The flag is set when the packet
+/// is the last of a frame.
+fn parse_ddp(packet: &[u8]) -> Option<(Data, bool)> {
+    if packet.len() < 10 {
+        return None;
+    }
+
+    let header = protocol::Header::from(packet);
+    let start = if header.packet_type.timecode { 14 } else { 10 };
+
+    if header.packet_type.query
+        || header.packet_type.reply
+        || header.id != protocol::ID::Default
+        || packet.len() < start
+    {
+        return None;
+    }
+
+    let data = &packet[start..];
+    let len = (header.length as usize).min(data.len());
+
+    Some((
+        Data {
+            offset: header.offset as usize,
+            data: data[..len].to_vec(),
+        },
+        header.packet_type.push,
+    ))
+}
+
+/// Get the channel data from an E1.31 data packet
+///
+/// Universes are laid out one after another from channel 1, universe
+/// 1 starting at channel 1 and universe 2 at `universe_size + 1`.
+/// Preview data and packets with alternate start codes are ignored.
+fn parse_e131(packet: &[u8], universe_size: usize) -> Option<(usize, Data)> {
+    if packet.len() <= E131_HEADER_LEN
+        || &packet[4..16] != b"ASC-E1.17\0\0\0"
+        || packet[18..22] != [0, 0, 0, 4]
+        || packet[40..44] != [0, 0, 0, 2]
+    {
+        return None;
+    }
+
+    let preview = packet[112] & 0x80 == 0x80;
+    let universe = u16::from_be_bytes([packet[113], packet[114]]) as usize;
+    let count = u16::from_be_bytes([packet[123], packet[124]]) as usize;
+    let start_code = packet[E131_HEADER_LEN];
+
+    if preview || universe == 0 || start_code != 0 {
+        return None;
+    }
+
+    let data = &packet[E131_HEADER_LEN + 1..];
+    let len = count.saturating_sub(1).min(data.len()).min(universe_size);
+
+    Some((
+        universe,
+        Data {
+            offset: (universe - 1) * universe_size,
+            data: data[..len].to_vec(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(offset: usize, data: &[u8]) -> Data {
+        Data {
+            offset,
+            data: data.to_vec(),
+        }
+    }
+
+    /// The frames sent after each universe in `universes` arrives,
+    /// each universe being a single channel set to its number
+    fn e131_frames(universes: &[usize]) -> Vec<Vec<(usize, Vec<u8>)>> {
+        let mut frame = E131Frame::new(8);
+        universes
+            .iter()
+            .map(|u| {
+                let mut ready = Vec::new();
+                frame.add(*u, data(u - 1, &[*u as u8]), &mut ready);
+                ready.into_iter().map(|d| (d.offset, d.data)).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn frame_sends_changed_channels() {
+        let mut frame = Frame::new(8);
+        assert!(frame.take().is_none());
+
+        frame.add(data(1, &[1, 2]));
+        frame.add(data(5, &[5]));
+        let sent = frame.take().unwrap();
+        assert_eq!((sent.offset, sent.data), (1, vec![1, 2, 0, 0, 5]));
+        assert!(frame.take().is_none());
+        assert!(frame.deadline().is_none());
+
+        // Unchanged channels keep their last values
+        frame.add(data(0, &[9]));
+        frame.add(data(3, &[3]));
+        let sent = frame.take().unwrap();
+        assert_eq!((sent.offset, sent.data), (0, vec![9, 1, 2, 3]));
+    }
+
+    #[test]
+    fn frame_drops_channels_past_the_end() {
+        let mut frame = Frame::new(4);
+
+        frame.add(data(usize::MAX, &[1]));
+        frame.add(data(8, &[1]));
+        assert!(frame.take().is_none());
+
+        frame.add(data(2, &[1, 2, 3, 4]));
+        let sent = frame.take().unwrap();
+        assert_eq!((sent.offset, sent.data), (2, vec![1, 2]));
+    }
+
+    #[test]
+    fn e131_frame_ends_at_the_last_universe() {
+        let frames = e131_frames(&[1, 2, 3, 1, 2, 3]);
+
+        // The first frame only ends when universe 1 repeats
+        assert!(frames[..3].iter().all(|f| f.is_empty()));
+        assert_eq!(frames[3], vec![(0, vec![1, 2, 3])]);
+        assert!(frames[4].is_empty());
+        assert_eq!(frames[5], vec![(0, vec![1, 2, 3])]);
+    }
+
+    #[test]
+    fn e131_frame_follows_more_universes() {
+        let frames = e131_frames(&[1, 2, 1, 2, 3, 1, 2, 3]);
+
+        assert_eq!(frames[2], vec![(0, vec![1, 2])]);
+        assert_eq!(frames[3], vec![(0, vec![1, 2])]);
+        // Universe 3 is new, so frames end with it from now on
+        assert_eq!(frames[4], vec![(2, vec![3])]);
+        assert!(frames[5].is_empty());
+        assert!(frames[6].is_empty());
+        assert_eq!(frames[7], vec![(0, vec![1, 2, 3])]);
+    }
+
+    #[test]
+    fn multicast_groups() {
+        assert_eq!(multicast_group(1), Ipv4Addr::new(239, 255, 0, 1));
+        assert_eq!(multicast_group(257), Ipv4Addr::new(239, 255, 1, 1));
+        assert_eq!(
+            multicast_group(E131_MAX_UNIVERSE),
+            Ipv4Addr::new(239, 255, 249, 255)
+        );
+    }
+}
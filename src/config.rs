@@ -39,12 +39,45 @@ pub struct Config {
     pub log: Option<LogConfig>,
     pub scheduler: Option<SchedulerConfig>,
     pub buttons: Option<Vec<ButtonConfig>>,
+    pub auth: Option<AuthConfig>,
+    pub output: Option<OutputConfig>,
+    pub bridge: Option<BridgeConfig>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct WebConfig {
     pub bind: Option<Ipv4Addr>,
     pub port: Option<u16>,
+    /// Largest upload accepted in MiB
+    pub max_upload_mb: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutputConfig {
+    /// Address to send DDP data from
+    pub bind: Option<Ipv4Addr>,
+    /// Port to send DDP data from, any free port if not set
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct BridgeConfig {
+    pub ddp_port: Option<u16>,
+    pub e131_port: Option<u16>,
+    /// Channels in each E1.31 universe
+    pub e131_universe_size: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthConfig {
+    pub enabled: Option<bool>,
+    /// The admin user created when there are no users
+    pub admin_username: Option<String>,
+    pub admin_password: Option<String>,
+    pub session_hours: Option<u64>,
+    /// Addresses or networks (`192.168.1.0/24`) allowed to use the
+    /// FPP compatible endpoints without logging in
+    pub fpp_allow: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -3,12 +3,13 @@ use std::path::Path;
 use anyhow::{anyhow, bail, Result};
 use chrono::{Datelike, Timelike};
 use diesel::{
-    result::Error::NotFound, sqlite::Sqlite, BelongingToDsl, Connection, ExpressionMethods,
-    Insertable, QueryDsl, RunQueryDsl, SelectableHelper, SqliteConnection,
+    result::Error::NotFound, sqlite::Sqlite, BelongingToDsl, BoolExpressionMethods, Connection,
+    EscapeExpressionMethods, ExpressionMethods, GroupedBy, Insertable, QueryDsl, RunQueryDsl,
+    SelectableHelper, SqliteConnection, TextExpressionMethods,
 };
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 
-use crate::{config::Config, fseq};
+use crate::{config::Config, fseq, models::SequenceSort};
 use models::*;
 use schema::*;
 
@@ -62,18 +63,25 @@ pub fn get_sequence(
     }
 }
 
-pub fn new_sequence(conn: &mut SqliteConnection, seq: fseq::parser::FSeq) -> Result<()> {
+pub fn new_sequence(
+    conn: &mut SqliteConnection,
+    seq: fseq::parser::FSeq,
+    checksum: String,
+    warnings: Vec<String>,
+) -> Result<()> {
     let filename = match Path::new(&seq.filename).file_name() {
         Some(f) => f.to_string_lossy().to_string(),
         None => "".to_string(),
     };
 
     let ns = NewSequence {
-        name: filename,
+        name: filename.clone(),
         timestamp: seq.uuid.to_string(),
         step_time: seq.step_time_ms as i32,
         frames: seq.frame_count as i32,
         channels: seq.channel_count as i32,
+        checksum,
+        uploaded: chrono::Utc::now().timestamp(),
     };
 
     diesel::insert_into(sequences::table)
@@ -84,24 +92,51 @@ pub fn new_sequence(conn: &mut SqliteConnection, seq: fseq::parser::FSeq) -> Res
         .execute(conn)?;
 
     let id = sequences::table
-        .select(diesel::dsl::max(sequences::id))
-        .first::<Option<i32>>(conn)?;
+        .filter(sequences::name.eq(filename))
+        .select(sequences::id)
+        .first::<i32>(conn)?;
 
-    if let Some(id) = id {
-        for v in seq.variables.into_iter() {
-            NewVariable {
-                sequence_id: id,
-                name: v.code,
-                value: v.data,
-            }
-            .insert_into(variables::table)
-            .execute(conn)?;
+    // Replace anything left from a previous upload
+    diesel::delete(variables::table.filter(variables::sequence_id.eq(id))).execute(conn)?;
+    diesel::delete(warnings::table.filter(warnings::sequence_id.eq(id))).execute(conn)?;
+
+    for v in seq.variables.into_iter() {
+        NewVariable {
+            sequence_id: id,
+            name: v.code,
+            value: v.data,
         }
+        .insert_into(variables::table)
+        .execute(conn)?;
+    }
+
+    for message in warnings.into_iter() {
+        NewWarning {
+            sequence_id: id,
+            message,
+        }
+        .insert_into(warnings::table)
+        .execute(conn)?;
     }
 
     Ok(())
 }
 
+pub fn get_sequence_warnings(conn: &mut SqliteConnection, seq: &Sequence) -> Result<Vec<String>> {
+    Warning::belonging_to(seq)
+        .select(warnings::message)
+        .load(conn)
+        .map_err(|e| anyhow!(e))
+}
+
+pub fn get_sequence_tags(conn: &mut SqliteConnection, seq: &Sequence) -> Result<Vec<String>> {
+    Tag::belonging_to(seq)
+        .select(tags::name)
+        .order(tags::name.asc())
+        .load(conn)
+        .map_err(|e| anyhow!(e))
+}
+
 pub fn del_sequence(conn: &mut SqliteConnection, n: String) -> Result<Option<()>> {
     match sequences::table
         .filter(sequences::name.eq(n))
@@ -112,6 +147,9 @@ pub fn del_sequence(conn: &mut SqliteConnection, n: String) -> Result<Option<()>
             diesel::delete(sequences::table.filter(sequences::id.eq(seq_id))).execute(conn)?;
             diesel::delete(variables::table.filter(variables::sequence_id.eq(seq_id)))
                 .execute(conn)?;
+            diesel::delete(warnings::table.filter(warnings::sequence_id.eq(seq_id)))
+                .execute(conn)?;
+            diesel::delete(tags::table.filter(tags::sequence_id.eq(seq_id))).execute(conn)?;
             Ok(Some(()))
         }
         Err(NotFound) => Ok(None),
@@ -119,6 +157,163 @@ pub fn del_sequence(conn: &mut SqliteConnection, n: String) -> Result<Option<()>
     }
 }
 
+/// Find sequences whose name or variables contain `text` and which
+/// have the given tag, along with their tags
+pub fn search_sequences(
+    conn: &mut SqliteConnection,
+    text: Option<String>,
+    tag: Option<String>,
+    sort: SequenceSort,
+    desc: bool,
+) -> Result<Vec<(Sequence, Vec<String>)>> {
+    let mut query = sequences::table.into_boxed();
+
+    if let Some(text) = text.filter(|t| !t.is_empty()) {
+        let pattern = format!(
+            "%{}%",
+            text.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let in_vars = variables::table
+            .filter(variables::value.like(pattern.clone()).escape('\\'))
+            .select(variables::sequence_id);
+
+        query = query.filter(
+            sequences::name
+                .like(pattern)
+                .escape('\\')
+                .or(sequences::id.eq_any(in_vars)),
+        );
+    }
+
+    if let Some(tag) = tag {
+        let tagged = tags::table
+            .filter(tags::name.eq(tag))
+            .select(tags::sequence_id);
+        query = query.filter(sequences::id.eq_any(tagged));
+    }
+
+    let duration = sequences::frames * sequences::step_time;
+    query = match (sort, desc) {
+        (SequenceSort::Name, false) => query.order(sequences::name.asc()),
+        (SequenceSort::Name, true) => query.order(sequences::name.desc()),
+        (SequenceSort::Duration, false) => query.order((duration.asc(), sequences::name.asc())),
+        (SequenceSort::Duration, true) => query.order((duration.desc(), sequences::name.asc())),
+        (SequenceSort::Uploaded, false) => {
+            query.order((sequences::uploaded.asc(), sequences::name.asc()))
+        }
+        (SequenceSort::Uploaded, true) => {
+            query.order((sequences::uploaded.desc(), sequences::name.asc()))
+        }
+    };
+
+    let seqs = query.select(Sequence::as_select()).load(conn)?;
+    let tags = Tag::belonging_to(&seqs)
+        .select(Tag::as_select())
+        .order(tags::name.asc())
+        .load(conn)?
+        .grouped_by(&seqs);
+
+    Ok(seqs
+        .into_iter()
+        .zip(tags)
+        .map(|(s, t)| (s, t.into_iter().map(|t| t.name).collect()))
+        .collect())
+}
+
+/// All tags in use
+pub fn get_tags(conn: &mut SqliteConnection) -> Result<Vec<String>> {
+    tags::table
+        .select(tags::name)
+        .distinct()
+        .order(tags::name.asc())
+        .load(conn)
+        .map_err(|e| anyhow!(e))
+}
+
+/// Replace the tags of a sequence
+pub fn set_sequence_tags(
+    conn: &mut SqliteConnection,
+    name: String,
+    tags: Vec<String>,
+) -> Result<Option<()>> {
+    let id = match sequences::table
+        .filter(sequences::name.eq(name))
+        .select(sequences::id)
+        .first::<i32>(conn)
+    {
+        Ok(id) => id,
+        Err(NotFound) => return Ok(None),
+        Err(e) => return Err(anyhow!(e)),
+    };
+
+    conn.transaction(|conn| {
+        diesel::delete(tags::table.filter(tags::sequence_id.eq(id))).execute(conn)?;
+
+        let new = tags
+            .into_iter()
+            .map(|name| Tag {
+                sequence_id: id,
+                name,
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_or_ignore_into(tags::table)
+            .values(&new)
+            .execute(conn)?;
+
+        Ok(Some(()))
+    })
+}
+
+/// Rename a sequence, calling `rename_file` to move the file once the
+/// database has been updated so both are changed or neither is
+///
+/// Playlists refer to sequences by id so only buttons need updating.
+pub fn rename_sequence(
+    conn: &mut SqliteConnection,
+    from: String,
+    to: String,
+    rename_file: impl FnOnce() -> Result<()>,
+) -> Result<Option<()>> {
+    conn.transaction(|conn| {
+        let updated = diesel::update(sequences::table.filter(sequences::name.eq(&from)))
+            .set(sequences::name.eq(&to))
+            .execute(conn)?;
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        diesel::update(
+            buttons::table
+                .filter(buttons::action.eq(Action::Sequence))
+                .filter(buttons::action_target.eq(&from)),
+        )
+        .set(buttons::action_target.eq(&to))
+        .execute(conn)?;
+
+        rename_file()?;
+
+        Ok(Some(()))
+    })
+}
+
+/// Set the percentage brightness a sequence is played at
+pub fn set_sequence_brightness(
+    conn: &mut SqliteConnection,
+    name: String,
+    brightness: i32,
+) -> Result<Option<()>> {
+    match diesel::update(sequences::table.filter(sequences::name.eq(name)))
+        .set(sequences::brightness.eq(brightness))
+        .execute(conn)
+    {
+        Ok(0) => Ok(None),
+        Ok(_) => Ok(Some(())),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
 pub fn get_playlists(conn: &mut SqliteConnection) -> Result<Vec<Playlist>> {
     playlists::table
         .load::<Playlist>(conn)
@@ -146,9 +341,10 @@ pub fn get_playlist(
         .select((
             playlists_sequences::enabled,
             playlists_sequences::play_once,
+            playlists_sequences::brightness,
             Sequence::as_select(),
         ))
-        .load::<(bool, bool, Sequence)>(conn)
+        .load::<(bool, bool, i32, Sequence)>(conn)
     {
         Ok(v) => v,
         Err(NotFound) => return Ok(None),
@@ -157,9 +353,10 @@ pub fn get_playlist(
 
     let sequences = sequences
         .into_iter()
-        .map(|(enabled, play_once, seq)| SequencePlus {
+        .map(|(enabled, play_once, brightness, seq)| SequencePlus {
             enabled,
             play_once,
+            brightness,
             sequence: seq,
         })
         .collect();
@@ -215,6 +412,7 @@ pub fn new_playlist(
             sort_by: i as i32,
             enabled: s.enabled,
             play_once: s.play_once,
+            brightness: s.brightness,
         };
 
         if let Err(e) = diesel::insert_into(playlists_sequences::table)
@@ -298,18 +496,100 @@ pub fn get_current_schedule(conn: &mut SqliteConnection) -> Result<Option<NextSc
     let sequences = match playlists_sequences::table
         .filter(playlists_sequences::playlist_id.eq(playlist.id))
         .inner_join(sequences::table)
-        .select((playlists_sequences::play_once, Sequence::as_select()))
+        .select((
+            playlists_sequences::enabled,
+            playlists_sequences::play_once,
+            playlists_sequences::brightness,
+            Sequence::as_select(),
+        ))
         .order_by(playlists_sequences::sort_by.asc())
-        .load::<(bool, Sequence)>(conn)
+        .load::<(bool, bool, i32, Sequence)>(conn)
     {
         Ok(v) => v,
         Err(NotFound) => return Ok(None),
         Err(e) => return Err(anyhow!(e)),
     };
 
+    let sequences = sequences
+        .into_iter()
+        .map(|(enabled, play_once, brightness, seq)| SequencePlus {
+            enabled,
+            play_once,
+            brightness,
+            sequence: seq,
+        })
+        .collect();
+
     Ok(Some((schedule, playlist, sequences)))
 }
 
+/// Find the schedule that will start next within the coming week
+pub fn get_next_schedule(
+    conn: &mut SqliteConnection,
+) -> Result<Option<(Schedule, Playlist, chrono::NaiveDateTime)>> {
+    let now = chrono::offset::Local::now().naive_local();
+
+    let schedules = schedules::table
+        .filter(schedules::enabled.eq(true))
+        .filter(schedules::end_date.ge(now.date().num_days_from_ce()))
+        .select(Schedule::as_select())
+        .load(conn)
+        .map_err(|e| anyhow!(e))?;
+
+    let mut next: Option<(Schedule, chrono::NaiveDateTime)> = None;
+    for schedule in schedules {
+        let start = (0..=7).find_map(|offset| {
+            let date = now.date() + chrono::Days::new(offset);
+            let days = date.num_days_from_ce();
+            if days < schedule.start_date || days > schedule.end_date {
+                return None;
+            }
+
+            let runs = match date.weekday() {
+                chrono::Weekday::Mon => schedule.monday,
+                chrono::Weekday::Tue => schedule.tuesday,
+                chrono::Weekday::Wed => schedule.wednesday,
+                chrono::Weekday::Thu => schedule.thursday,
+                chrono::Weekday::Fri => schedule.friday,
+                chrono::Weekday::Sat => schedule.saturday,
+                chrono::Weekday::Sun => schedule.sunday,
+            };
+            if !runs {
+                return None;
+            }
+
+            let time = chrono::NaiveTime::from_num_seconds_from_midnight_opt(
+                schedule.start_time as u32,
+                0,
+            )?;
+            let start = date.and_time(time);
+            (start > now).then_some(start)
+        });
+
+        if let Some(start) = start {
+            if next.as_ref().is_none_or(|(_, s)| start < *s) {
+                next = Some((schedule, start));
+            }
+        }
+    }
+
+    let Some((schedule, start)) = next else {
+        return Ok(None);
+    };
+
+    let playlist = match playlists::table
+        .filter(playlists::id.eq(schedule.playlist_id))
+        .select(Playlist::as_select())
+        .first(conn)
+    {
+        Ok(p) => p,
+        Err(NotFound) => return Ok(None),
+        Err(e) => return Err(anyhow!(e)),
+    };
+
+    Ok(Some((schedule, playlist, start)))
+}
+
 pub fn get_schedules(conn: &mut SqliteConnection) -> Result<Vec<Schedule>> {
     schedules::table
         .load::<Schedule>(conn)
@@ -479,3 +759,176 @@ pub fn del_button(conn: &mut SqliteConnection, id: i32) -> Result<Option<()>> {
         Err(e) => Err(anyhow!(e)),
     }
 }
+
+pub fn count_users(conn: &mut SqliteConnection) -> Result<i64> {
+    users::table
+        .count()
+        .get_result(conn)
+        .map_err(|e| anyhow!(e))
+}
+
+pub fn get_users(conn: &mut SqliteConnection) -> Result<Vec<User>> {
+    users::table
+        .order(users::username.asc())
+        .select(User::as_select())
+        .load(conn)
+        .map_err(|e| anyhow!(e))
+}
+
+pub fn get_user(conn: &mut SqliteConnection, username: &str) -> Result<Option<User>> {
+    match users::table
+        .filter(users::username.eq(username))
+        .select(User::as_select())
+        .first(conn)
+    {
+        Ok(u) => Ok(Some(u)),
+        Err(NotFound) => Ok(None),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+pub fn new_user(conn: &mut SqliteConnection, user: NewUser) -> Result<()> {
+    diesel::insert_into(users::table)
+        .values(&user)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn update_user(
+    conn: &mut SqliteConnection,
+    username: &str,
+    password_hash: Option<String>,
+    role: Option<Role>,
+) -> Result<Option<()>> {
+    let user = match get_user(conn, username)? {
+        Some(u) => u,
+        None => return Ok(None),
+    };
+
+    if let Some(hash) = password_hash {
+        diesel::update(users::table.filter(users::id.eq(user.id)))
+            .set(users::password_hash.eq(hash))
+            .execute(conn)?;
+
+        // Changing the password logs the user out everywhere
+        diesel::delete(sessions::table.filter(sessions::user_id.eq(user.id))).execute(conn)?;
+    }
+
+    if let Some(role) = role {
+        diesel::update(users::table.filter(users::id.eq(user.id)))
+            .set(users::role.eq(role))
+            .execute(conn)?;
+    }
+
+    Ok(Some(()))
+}
+
+pub fn del_user(conn: &mut SqliteConnection, username: &str) -> Result<Option<()>> {
+    let user = match get_user(conn, username)? {
+        Some(u) => u,
+        None => return Ok(None),
+    };
+
+    diesel::delete(sessions::table.filter(sessions::user_id.eq(user.id))).execute(conn)?;
+    diesel::delete(users::table.filter(users::id.eq(user.id))).execute(conn)?;
+
+    Ok(Some(()))
+}
+
+pub fn new_session(conn: &mut SqliteConnection, session: NewSession) -> Result<()> {
+    let now = chrono::Utc::now().timestamp();
+
+    // Tidy up any sessions which have already expired
+    diesel::delete(sessions::table.filter(sessions::expires.lt(now))).execute(conn)?;
+
+    diesel::insert_into(sessions::table)
+        .values(&session)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn get_session_user(conn: &mut SqliteConnection, token_hash: &str) -> Result<Option<User>> {
+    let now = chrono::Utc::now().timestamp();
+
+    match sessions::table
+        .inner_join(users::table)
+        .filter(sessions::token_hash.eq(token_hash))
+        .filter(sessions::expires.ge(now))
+        .select(User::as_select())
+        .first(conn)
+    {
+        Ok(u) => Ok(Some(u)),
+        Err(NotFound) => Ok(None),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+pub fn del_session(conn: &mut SqliteConnection, token_hash: &str) -> Result<()> {
+    diesel::delete(sessions::table.filter(sessions::token_hash.eq(token_hash))).execute(conn)?;
+
+    Ok(())
+}
+
+pub fn get_tokens(conn: &mut SqliteConnection) -> Result<Vec<ApiToken>> {
+    api_tokens::table
+        .order(api_tokens::name.asc())
+        .select(ApiToken::as_select())
+        .load(conn)
+        .map_err(|e| anyhow!(e))
+}
+
+pub fn get_token(conn: &mut SqliteConnection, token_hash: &str) -> Result<Option<ApiToken>> {
+    match api_tokens::table
+        .filter(api_tokens::token_hash.eq(token_hash))
+        .select(ApiToken::as_select())
+        .first(conn)
+    {
+        Ok(t) => Ok(Some(t)),
+        Err(NotFound) => Ok(None),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+pub fn new_token(conn: &mut SqliteConnection, token: NewApiToken) -> Result<()> {
+    diesel::insert_into(api_tokens::table)
+        .values(&token)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn del_token(conn: &mut SqliteConnection, name: &str) -> Result<Option<()>> {
+    match diesel::delete(api_tokens::table.filter(api_tokens::name.eq(name))).execute(conn) {
+        Ok(0) => Ok(None),
+        Ok(_) => Ok(Some(())),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+pub fn get_setting(conn: &mut SqliteConnection, name: &str) -> Result<Option<String>> {
+    match settings::table
+        .filter(settings::name.eq(name))
+        .select(settings::value)
+        .first(conn)
+    {
+        Ok(v) => Ok(Some(v)),
+        Err(NotFound) => Ok(None),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+pub fn set_setting(conn: &mut SqliteConnection, name: &str, value: String) -> Result<()> {
+    diesel::insert_into(settings::table)
+        .values(Setting {
+            name: name.to_string(),
+            value: value.clone(),
+        })
+        .on_conflict(settings::name)
+        .do_update()
+        .set(settings::value.eq(value))
+        .execute(conn)?;
+
+    Ok(())
+}
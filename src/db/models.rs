@@ -20,6 +20,30 @@ pub enum Action {
     Stop,
 }
 
+/// Access levels, each including everything the previous one can do
+#[derive(
+    diesel_derive_enum::DbEnum,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Can see everything but not change anything
+    Viewer,
+    /// Can control the player and edit playlists and schedules
+    Operator,
+    /// Can do anything, including uploads and managing users
+    Admin,
+}
+
 #[derive(Queryable, Selectable, Identifiable, PartialEq, Debug, Serialize, ToSchema)]
 #[diesel(table_name = schema::buttons)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
@@ -158,6 +182,11 @@ pub struct Sequence {
     pub step_time: i32,
     pub frames: i32,
     pub channels: i32,
+    pub checksum: String,
+    /// Unix timestamp of when the file was uploaded
+    pub uploaded: i64,
+    /// Percentage brightness to play the sequence at
+    pub brightness: i32,
 }
 
 #[derive(Queryable, Selectable, Identifiable, Associations, PartialEq, Debug)]
@@ -195,6 +224,26 @@ pub struct Variable {
     pub value: String,
 }
 
+#[derive(Queryable, Selectable, Identifiable, Associations, PartialEq, Debug)]
+#[diesel(belongs_to(Sequence))]
+#[diesel(table_name = schema::warnings)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Warning {
+    pub id: i32,
+    pub sequence_id: i32,
+    pub message: String,
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, PartialEq, Debug)]
+#[diesel(belongs_to(Sequence))]
+#[diesel(table_name = schema::tags)]
+#[diesel(primary_key(sequence_id, name))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Tag {
+    pub sequence_id: i32,
+    pub name: String,
+}
+
 #[derive(Insertable, Identifiable, Selectable, Queryable, Associations, Debug, AsChangeset)]
 #[diesel(belongs_to(Playlist))]
 #[diesel(belongs_to(Sequence))]
@@ -206,6 +255,7 @@ pub struct PlaylistSequence {
     pub sort_by: i32,
     pub enabled: bool,
     pub play_once: bool,
+    pub brightness: i32,
 }
 
 #[derive(Insertable, PartialEq, Debug, Deserialize, AsChangeset)]
@@ -217,6 +267,8 @@ pub struct NewSequence {
     pub step_time: i32,
     pub frames: i32,
     pub channels: i32,
+    pub checksum: String,
+    pub uploaded: i64,
 }
 
 #[derive(Insertable, PartialEq, Debug, AsChangeset)]
@@ -250,18 +302,84 @@ pub struct NewVariable {
     pub value: String,
 }
 
+#[derive(Insertable, PartialEq, Debug)]
+#[diesel(table_name = schema::warnings)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct NewWarning {
+    pub sequence_id: i32,
+    pub message: String,
+}
+
 pub struct SequencePlus {
     pub enabled: bool,
     pub play_once: bool,
+    pub brightness: i32,
     pub sequence: Sequence,
 }
 
 pub struct NewSequencePlus {
     pub enabled: bool,
     pub play_once: bool,
+    pub brightness: i32,
     pub sequence: String,
 }
 
 pub type PlaylistAndSeq = (Playlist, Vec<SequencePlus>);
 pub type NewPlaylistAndSeq = (NewPlaylist, Vec<NewSequencePlus>);
-pub type NextSchedule = (Schedule, Playlist, Vec<(bool, Sequence)>);
+pub type NextSchedule = (Schedule, Playlist, Vec<SequencePlus>);
+
+#[derive(Insertable, Queryable, Selectable, PartialEq, Debug)]
+#[diesel(table_name = schema::settings)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Setting {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Queryable, Selectable, Identifiable, PartialEq, Debug)]
+#[diesel(table_name = schema::users)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+    pub password_hash: String,
+    pub role: Role,
+}
+
+#[derive(Insertable, PartialEq, Debug)]
+#[diesel(table_name = schema::users)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct NewUser {
+    pub username: String,
+    pub password_hash: String,
+    pub role: Role,
+}
+
+#[derive(Insertable, PartialEq, Debug)]
+#[diesel(table_name = schema::sessions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct NewSession {
+    pub token_hash: String,
+    pub user_id: i32,
+    pub expires: i64,
+}
+
+#[derive(Queryable, Selectable, Identifiable, PartialEq, Debug, Serialize, ToSchema)]
+#[diesel(table_name = schema::api_tokens)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub role: Role,
+}
+
+#[derive(Insertable, PartialEq, Debug)]
+#[diesel(table_name = schema::api_tokens)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct NewApiToken {
+    pub name: String,
+    pub token_hash: String,
+    pub role: Role,
+}
@@ -1,5 +1,17 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    use diesel::sql_types::{Integer, Text};
+    use crate::db::models::RoleMapping;
+
+    api_tokens (id) {
+        id -> Integer,
+        name -> Text,
+        token_hash -> Text,
+        role -> RoleMapping,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::{Integer, Text, Float, Bool};
     use crate::db::models::ActionMapping;
@@ -50,6 +62,7 @@ diesel::table! {
         sort_by -> Integer,
         enabled -> Bool,
         play_once -> Bool,
+        brightness -> Integer,
     }
 }
 
@@ -90,6 +103,15 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    sessions (id) {
+        id -> Integer,
+        token_hash -> Text,
+        user_id -> Integer,
+        expires -> BigInt,
+    }
+}
+
 diesel::table! {
     sequences (id) {
         id -> Integer,
@@ -98,6 +120,35 @@ diesel::table! {
         step_time -> Integer,
         frames -> Integer,
         channels -> Integer,
+        checksum -> Text,
+        uploaded -> BigInt,
+        brightness -> Integer,
+    }
+}
+
+diesel::table! {
+    settings (name) {
+        name -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::{Integer, Text};
+    use crate::db::models::RoleMapping;
+
+    users (id) {
+        id -> Integer,
+        username -> Text,
+        password_hash -> Text,
+        role -> RoleMapping,
+    }
+}
+
+diesel::table! {
+    tags (sequence_id, name) {
+        sequence_id -> Integer,
+        name -> Text,
     }
 }
 
@@ -110,12 +161,24 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    warnings (id) {
+        id -> Integer,
+        sequence_id -> Integer,
+        message -> Text,
+    }
+}
+
 diesel::joinable!(playlists_sequences -> playlists (playlist_id));
 diesel::joinable!(playlists_sequences -> sequences (sequence_id));
 diesel::joinable!(schedules -> playlists (playlist_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(tags -> sequences (sequence_id));
 diesel::joinable!(variables -> sequences (sequence_id));
+diesel::joinable!(warnings -> sequences (sequence_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    api_tokens,
     buttons,
     meshes,
     playlists,
@@ -123,5 +186,10 @@ diesel::allow_tables_to_appear_in_same_query!(
     scenes,
     schedules,
     sequences,
+    sessions,
+    settings,
+    tags,
+    users,
     variables,
+    warnings,
 );
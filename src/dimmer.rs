@@ -0,0 +1,67 @@
+/// Name of the setting holding the master brightness
+pub const MASTER_SETTING: &str = "brightness";
+
+/// Exponent relating channel values to how bright they look
+const GAMMA: f32 = 2.2;
+
+/// Scales channel data to a percentage brightness
+///
+/// Brightness is perceptual so 50% looks half as bright rather than
+/// halving every channel value, which barely looks dimmer at all.
+pub struct Dimmer {
+    percent: u8,
+    lut: [u8; 256],
+}
+
+impl Default for Dimmer {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl Dimmer {
+    pub fn new(percent: u8) -> Self {
+        let mut dimmer = Self {
+            percent: 100,
+            lut: std::array::from_fn(|i| i as u8),
+        };
+        dimmer.set(percent);
+        dimmer
+    }
+
+    /// Change the brightness, only rebuilding the lookup table if it
+    /// changed
+    pub fn set(&mut self, percent: u8) {
+        let percent = percent.min(100);
+        if percent == self.percent {
+            return;
+        }
+
+        let scale = (percent as f32 / 100.0).powf(GAMMA);
+        for (i, v) in self.lut.iter_mut().enumerate() {
+            *v = (i as f32 * scale).round() as u8;
+        }
+        self.percent = percent;
+    }
+
+    pub fn apply(&self, data: &mut [u8]) {
+        if self.percent == 100 {
+            return;
+        }
+
+        for v in data.iter_mut() {
+            *v = self.lut[*v as usize];
+        }
+    }
+}
+
+/// Combine brightness percentages, e.g. the master brightness with
+/// that of a sequence
+pub fn combine(levels: &[i32]) -> u8 {
+    let scale = levels
+        .iter()
+        .map(|&l| l.clamp(0, 100) as f32 / 100.0)
+        .product::<f32>();
+
+    (scale * 100.0).round() as u8
+}
@@ -0,0 +1,444 @@
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// The virtual display map exported by xLights
+#[derive(Debug, Default, PartialEq, Serialize, Clone, ToSchema)]
+pub struct VirtualDisplay {
+    /// Width of the xLights preview
+    #[schema(example = 1920)]
+    pub width: u32,
+    /// Height of the xLights preview
+    #[schema(example = 1080)]
+    pub height: u32,
+    /// The models making up the display
+    pub models: Vec<DisplayModel>,
+    /// Every pixel in the display
+    pub pixels: Vec<Pixel>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Clone, ToSchema)]
+pub struct DisplayModel {
+    #[schema(example = "Arch 1")]
+    pub name: String,
+    /// Index of the first pixel of the model
+    #[schema(example = 0)]
+    pub first_pixel: usize,
+    /// Number of pixels in the model
+    #[schema(example = 50)]
+    pub pixel_count: usize,
+    /// Zero based offset of the first channel of the model
+    #[schema(example = 0)]
+    pub start_channel: usize,
+    /// Number of channels used by the model
+    #[schema(example = 150)]
+    pub channel_count: usize,
+    /// The space occupied by the model
+    pub bounds: BoundingBox,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Clone, ToSchema)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Clone, Copy, ToSchema)]
+pub struct Point {
+    #[schema(example = 100.0)]
+    pub x: f32,
+    #[schema(example = 200.0)]
+    pub y: f32,
+    #[schema(example = 0.0)]
+    pub z: f32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone, ToSchema)]
+pub struct Pixel {
+    #[schema(example = 100.0)]
+    pub x: f32,
+    #[schema(example = 200.0)]
+    pub y: f32,
+    #[schema(example = 0.0)]
+    pub z: f32,
+    /// Zero based offset of the first channel of the pixel
+    #[schema(example = 0)]
+    pub channel: usize,
+    /// Number of channels making up the pixel
+    #[schema(example = 3)]
+    pub channels: usize,
+    /// Order of the colours in the channels
+    #[schema(example = "RGB")]
+    pub color_order: String,
+}
+
+impl Pixel {
+    /// Get the RGB colour of the pixel from a frame of channel data
+    pub fn color(&self, frame: &[u8]) -> [u8; 3] {
+        let get = |i: usize| frame.get(self.channel + i).copied().unwrap_or(0);
+
+        if self.channels == 1 {
+            let v = get(0);
+            return [v, v, v];
+        }
+
+        let order = match self.color_order.is_empty() {
+            true => "RGB",
+            false => self.color_order.as_str(),
+        };
+
+        let mut rgb = [0u8; 3];
+        for (i, c) in order.chars().take(self.channels).enumerate() {
+            let v = get(i);
+            match c.to_ascii_uppercase() {
+                'R' => rgb[0] = v,
+                'G' => rgb[1] = v,
+                'B' => rgb[2] = v,
+                'W' => rgb.iter_mut().for_each(|c| *c = c.saturating_add(v)),
+                _ => {}
+            }
+        }
+
+        rgb
+    }
+}
+
+/// Parse the contents of a virtual display map
+///
+/// Comment lines start with `#`, apart from the `# Preview Size: WxH`
+/// header and the `# Model: 'Name', N nodes` lines which start each
+/// model. Every other line describes a pixel as
+/// `x,y,z,channel,channels,colorOrder`.
+///
+/// xLights writes a line for every light, so nodes with several lights
+/// and custom models don't always match the node count, and colour
+/// orders aren't always made of RGBW. Those are logged as warnings,
+/// only lines which can't be read at all are errors.
+pub fn parse(contents: &str) -> Result<VirtualDisplay> {
+    let mut display = VirtualDisplay::default();
+    // The model being read and the number of nodes it should have
+    let mut current: Option<(DisplayModel, usize)> = None;
+    // Each warning only once, there can be one for every pixel
+    let mut warnings = BTreeSet::new();
+
+    for (idx, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix('#') {
+            let comment = comment.trim();
+            if let Some(size) = comment.strip_prefix("Preview Size:") {
+                if let Some((w, h)) = size.trim().split_once('x') {
+                    display.width = w.trim().parse().unwrap_or_default();
+                    display.height = h.trim().parse().unwrap_or_default();
+                }
+            } else if let Some(model) = comment.strip_prefix("Model:") {
+                if let Some(prev) = current.take() {
+                    display.models.push(finish_model(prev, &mut warnings));
+                }
+                current = Some(
+                    parse_model(model, display.pixels.len())
+                        .with_context(|| format!("Line {}: invalid model", idx + 1))?,
+                );
+            }
+            continue;
+        }
+
+        let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+        if fields.len() < 5 {
+            bail!("Line {}: expected at least 5 fields", idx + 1);
+        }
+
+        let float = |i: usize| {
+            fields[i]
+                .parse::<f32>()
+                .with_context(|| format!("Line {}: invalid coordinate '{}'", idx + 1, fields[i]))
+        };
+        let int = |i: usize| {
+            fields[i]
+                .parse::<usize>()
+                .with_context(|| format!("Line {}: invalid channel '{}'", idx + 1, fields[i]))
+        };
+
+        let pixel = Pixel {
+            x: float(0)?,
+            y: float(1)?,
+            z: float(2)?,
+            channel: int(3)?,
+            channels: int(4)?,
+            color_order: fields.get(5).copied().unwrap_or_default().to_string(),
+        };
+
+        if !(1..=4).contains(&pixel.channels) {
+            warnings.insert(format!(
+                "Unsupported channels per pixel '{}', from line {}",
+                pixel.channels,
+                idx + 1
+            ));
+        } else if pixel.channels > 1
+            && (pixel.color_order.len() != pixel.channels
+                || !pixel
+                    .color_order
+                    .chars()
+                    .all(|c| "RGBW".contains(c.to_ascii_uppercase())))
+        {
+            warnings.insert(format!(
+                "Unknown colour order '{}' for {} channels, from line {}",
+                pixel.color_order,
+                pixel.channels,
+                idx + 1
+            ));
+        }
+
+        if let Some((model, _)) = current.as_mut() {
+            model.add(&pixel);
+        }
+        display.pixels.push(pixel);
+    }
+
+    if let Some(prev) = current.take() {
+        display.models.push(finish_model(prev, &mut warnings));
+    }
+
+    for w in warnings {
+        tracing::warn!("Virtual display map: {w}");
+    }
+
+    Ok(display)
+}
+
+impl DisplayModel {
+    fn add(&mut self, pixel: &Pixel) {
+        let p = Point {
+            x: pixel.x,
+            y: pixel.y,
+            z: pixel.z,
+        };
+        let end = pixel.channel + pixel.channels;
+
+        if self.pixel_count == 0 {
+            self.bounds = BoundingBox { min: p, max: p };
+            self.start_channel = pixel.channel;
+            self.channel_count = pixel.channels;
+        } else {
+            let b = &mut self.bounds;
+            b.min.x = b.min.x.min(p.x);
+            b.min.y = b.min.y.min(p.y);
+            b.min.z = b.min.z.min(p.z);
+            b.max.x = b.max.x.max(p.x);
+            b.max.y = b.max.y.max(p.y);
+            b.max.z = b.max.z.max(p.z);
+
+            let last = (self.start_channel + self.channel_count).max(end);
+            self.start_channel = self.start_channel.min(pixel.channel);
+            self.channel_count = last - self.start_channel;
+        }
+
+        self.pixel_count += 1;
+    }
+}
+
+/// Parse `'Name', N nodes` from a model line
+fn parse_model(line: &str, first_pixel: usize) -> Result<(DisplayModel, usize)> {
+    let line = line.trim();
+    let (name, nodes) = match line.rsplit_once(',') {
+        Some((name, nodes)) => (name.trim(), Some(nodes.trim())),
+        None => (line, None),
+    };
+
+    let name = name.trim_matches('\'').to_string();
+    if name.is_empty() {
+        bail!("Model has no name");
+    }
+
+    let nodes = match nodes {
+        Some(n) => n
+            .trim_end_matches("nodes")
+            .trim_end_matches("node")
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid node count for '{name}'"))?,
+        None => 0,
+    };
+
+    Ok((
+        DisplayModel {
+            name,
+            first_pixel,
+            ..Default::default()
+        },
+        nodes,
+    ))
+}
+
+fn finish_model(
+    (model, nodes): (DisplayModel, usize),
+    warnings: &mut BTreeSet<String>,
+) -> DisplayModel {
+    if nodes != 0 && nodes != model.pixel_count {
+        warnings.insert(format!(
+            "Model '{}' has {nodes} nodes but {} pixels",
+            model.name, model.pixel_count
+        ));
+    }
+
+    model
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Laid out as xLights exports it, one line for every light. The
+    /// candy cane has two lights per node, the custom mini tree leaves
+    /// a node out and the flood is a single channel white model.
+    const SAMPLE: &str = "\
+# Preview Size: 1920x1080
+# Model: 'Arch 1', 5 nodes
+100,200,0,0,3,RGB
+125,240,0,3,3,RGB
+150,250,0,6,3,RGB
+175,240,0,9,3,RGB
+200,200,0,12,3,RGB
+# Model: 'Candy Cane', 3 nodes
+300,100,0,15,3,GRB
+300,110,0,15,3,GRB
+300,120,0,18,3,GRB
+300,130,0,18,3,GRB
+305,140,0,21,3,GRB
+310,145,0,21,3,GRB
+# Model: 'Mini Tree', 4 nodes
+400,100,-5,24,3,RGB
+390,120,0,27,3,RGB
+410,120,5,33,3,RGB
+# Model: 'Flood', 1 nodes
+500,50,0,36,1,Single Color White
+";
+
+    fn model<'a>(display: &'a VirtualDisplay, name: &str) -> &'a DisplayModel {
+        display.models.iter().find(|m| m.name == name).unwrap()
+    }
+
+    #[test]
+    fn parses_xlights_sample() {
+        let display = parse(SAMPLE).unwrap();
+
+        assert_eq!((display.width, display.height), (1920, 1080));
+        assert_eq!(display.pixels.len(), 15);
+        assert_eq!(
+            display
+                .models
+                .iter()
+                .map(|m| (m.name.as_str(), m.first_pixel, m.pixel_count))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Arch 1", 0, 5),
+                ("Candy Cane", 5, 6),
+                ("Mini Tree", 11, 3),
+                ("Flood", 14, 1),
+            ]
+        );
+
+        let arch = model(&display, "Arch 1");
+        assert_eq!((arch.start_channel, arch.channel_count), (0, 15));
+    }
+
+    #[test]
+    fn keeps_models_with_other_node_counts() {
+        let display = parse(SAMPLE).unwrap();
+
+        // Two lights for each of the 3 nodes
+        let cane = model(&display, "Candy Cane");
+        assert_eq!(
+            (cane.pixel_count, cane.start_channel, cane.channel_count),
+            (6, 15, 9)
+        );
+
+        // The node left out of the custom model is inside its channels
+        let tree = model(&display, "Mini Tree");
+        assert_eq!(
+            (tree.pixel_count, tree.start_channel, tree.channel_count),
+            (3, 24, 12)
+        );
+    }
+
+    #[test]
+    fn single_channel_models() {
+        let display = parse(SAMPLE).unwrap();
+
+        let flood = model(&display, "Flood");
+        assert_eq!((flood.start_channel, flood.channel_count), (36, 1));
+
+        let pixel = &display.pixels[flood.first_pixel];
+        assert_eq!(pixel.color_order, "Single Color White");
+        let mut frame = vec![0; 37];
+        frame[36] = 80;
+        assert_eq!(pixel.color(&frame), [80, 80, 80]);
+    }
+
+    #[test]
+    fn bounding_boxes() {
+        let display = parse(SAMPLE).unwrap();
+
+        let arch = model(&display, "Arch 1");
+        assert_eq!(
+            arch.bounds.min,
+            Point {
+                x: 100.0,
+                y: 200.0,
+                z: 0.0
+            }
+        );
+        assert_eq!(
+            arch.bounds.max,
+            Point {
+                x: 200.0,
+                y: 250.0,
+                z: 0.0
+            }
+        );
+
+        let tree = model(&display, "Mini Tree");
+        assert_eq!(
+            tree.bounds.min,
+            Point {
+                x: 390.0,
+                y: 100.0,
+                z: -5.0
+            }
+        );
+        assert_eq!(
+            tree.bounds.max,
+            Point {
+                x: 410.0,
+                y: 120.0,
+                z: 5.0
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_colour_orders() {
+        let display =
+            parse("0,0,0,0,4,RGB\n0,1,0,4,3,Custom\n0,2,0,7,5,RGBWW\n0,3,0,12,3").unwrap();
+
+        assert_eq!(display.pixels.len(), 4);
+        assert_eq!(display.pixels[1].color_order, "Custom");
+        // No colour order is read as RGB
+        let mut frame = vec![0; 15];
+        frame[12..].copy_from_slice(&[1, 2, 3]);
+        assert_eq!(display.pixels[3].color(&frame), [1, 2, 3]);
+    }
+
+    #[test]
+    fn rejects_unreadable_lines() {
+        assert!(parse("0,0,0,0,3,RGB\n0,0,zero,3,3,RGB").is_err());
+        assert!(parse("0,0,0,-3,3,RGB").is_err());
+        assert!(parse("0,0,0,0").is_err());
+        assert!(parse("# Model: 'Arch', lots of nodes").is_err());
+    }
+}
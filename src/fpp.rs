@@ -1,22 +1,28 @@
 use std::{
     ffi::CStr,
     io::Cursor,
-    net::{Ipv4Addr, SocketAddrV4},
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
     str::FromStr,
+    sync::Arc,
 };
 
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use parking_lot::Mutex;
 use socket2::{Domain, Protocol, Socket, Type};
 
 use anyhow::{bail, Result};
-use tokio::net::UdpSocket;
+use tokio::{net::UdpSocket, sync::mpsc::Sender};
 use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
+use crate::{models::PlayerState, state::State};
+
 /*
 * https://github.com/FalconChristmas/fpp/blob/master/docs/ControlProtocol.txt
 */
 
-pub async fn listen(cancel: CancellationToken) {
+pub async fn listen(state: Arc<Mutex<State>>, cancel: CancellationToken) {
+    let player_ctrl = state.lock().player_ctrl.clone();
+
     let mut ips = Vec::new();
     if let Ok(network_interfaces) = local_ip_address::list_afinet_netifas() {
         network_interfaces.iter().for_each(|(_name, ip)| {
@@ -34,10 +40,11 @@ pub async fn listen(cancel: CancellationToken) {
     let tracker = TaskTracker::new();
     for ip in ips.into_iter() {
         let cancel = cancel.clone();
+        let player_ctrl = player_ctrl.clone();
 
         tracker.spawn(async move {
             match Ipv4Addr::from_str(&ip) {
-                Ok(addr) => match listen_thread(addr, cancel.clone()).await {
+                Ok(addr) => match listen_thread(addr, player_ctrl, cancel.clone()).await {
                     Ok(_) => {}
                     Err(e) => {
                         cancel.cancel();
@@ -56,7 +63,11 @@ pub async fn listen(cancel: CancellationToken) {
     tracker.wait().await;
 }
 
-async fn listen_thread(bind: Ipv4Addr, cancel: CancellationToken) -> Result<()> {
+async fn listen_thread(
+    bind: Ipv4Addr,
+    player_ctrl: Sender<PlayerState>,
+    cancel: CancellationToken,
+) -> Result<()> {
     let multi_addr = SocketAddrV4::new(Ipv4Addr::new(239, 70, 80, 80), 32320);
     let addr = SocketAddrV4::new(bind, 32320);
     let all = Ipv4Addr::new(0, 0, 0, 0);
@@ -84,59 +95,115 @@ async fn listen_thread(bind: Ipv4Addr, cancel: CancellationToken) -> Result<()>
                 let (_len, from_addr) = res?;
                 let mut cur = Cursor::new(buf);
 
-                if let Ok(fpp) = FPP::read(&mut cur) {
-                    if fpp.packet_type != PacketType::Ping {
-                        continue;
-                    }
+                let fpp = match FPP::read(&mut cur) {
+                    Ok(fpp) => fpp,
+                    Err(_) => continue,
+                };
 
-                    if let PacketBody::Ping(ping) = fpp.body {
-                        if ping.sub_type != PingSubType::Discovery {
-                            continue;
-                        }
+                match fpp.body {
+                    PacketBody::Ping(ping) if ping.sub_type == PingSubType::Discovery => {
+                        tracing::info!("Multicast Discover Received from {} on {}", from_addr, addr);
+                        reply_discovery(&socket, bind, from_addr).await?;
                     }
-
-                    tracing::info!("Multicast Discover Received from {} on {}", from_addr, addr);
-
-                    let fpp = FPP {
-                        magic: 0x46505044,
-                        packet_type: PacketType::Ping,
-                        body: PacketBody::Ping(Ping {
-                            data_len: 200,
-                            ping_version: 2,
-                            sub_type: PingSubType::Ping,
-                            hardware_type: HardwareType::Fpp,
-                            major_version: 1,
-                            minor_version: 0,
-                            operating_mode: OperatingMode {
-                                bridge: false,
-                                player: true,
-                                multisync: false,
-                                remote: false,
-                            },
-                            ip_address: bind,
-                            hostname: "localhost".to_string(),
-                            version: "RSP".to_string(),
-                            hardware: "Fedora".to_string(),
-                            channels: "".to_string(),
-                        }),
-                    };
-
-                    let mut buf = Vec::new();
-                    fpp.write(&mut buf)?;
-                    match socket.send_to(buf.as_slice(), from_addr).await {
-                        Ok(_) => {
-                            tracing::info!("Replied to Multicast Discovery");
-                        }
-                        Err(e) => {
-                            tracing::error!("Failed to reply to Multicast Discovery: {}", e);
+                    PacketBody::Command(command) => {
+                        tracing::info!("Command '{}' received from {}", command.command, from_addr);
+
+                        match command.player_state() {
+                            Some(s) => {
+                                if let Err(e) = player_ctrl.send(s).await {
+                                    tracing::error!("Could not send command to player: {e}");
+                                }
+                            }
+                            None => tracing::warn!("Unsupported command: {}", command.command),
                         }
                     }
+                    _ => {}
                 }
             },
         }
     }
 }
 
+async fn reply_discovery(socket: &UdpSocket, bind: Ipv4Addr, to: SocketAddr) -> Result<()> {
+    let fpp = FPP {
+        magic: 0x46505044,
+        packet_type: PacketType::Ping,
+        body: PacketBody::Ping(Ping {
+            data_len: 200,
+            ping_version: 2,
+            sub_type: PingSubType::Ping,
+            hardware_type: HardwareType::Fpp,
+            major_version: 1,
+            minor_version: 0,
+            operating_mode: OperatingMode {
+                bridge: true,
+                player: true,
+                multisync: false,
+                remote: false,
+            },
+            ip_address: bind,
+            hostname: "localhost".to_string(),
+            version: "RSP".to_string(),
+            hardware: "Fedora".to_string(),
+            channels: "".to_string(),
+        }),
+    };
+
+    let mut buf = Vec::new();
+    fpp.write(&mut buf)?;
+    match socket.send_to(buf.as_slice(), to).await {
+        Ok(_) => {
+            tracing::info!("Replied to Multicast Discovery");
+        }
+        Err(e) => {
+            tracing::error!("Failed to reply to Multicast Discovery: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Map an FPP command onto a player state
+///
+/// Only the subset of FPP commands which make sense for LEDPlayr
+/// are supported, anything else returns `None`. Playlists always start
+/// from the first item, so the commands to start at a given or random
+/// item aren't supported. Effects are treated as sequences, adding the
+/// `.fseq` extension if it is missing.
+pub fn player_state(command: &str, args: &[String]) -> Option<PlayerState> {
+    match command {
+        "Start Playlist" => args.first().map(|p| PlayerState::Playlist(p.clone())),
+        "Effect Start" | "FSEQ Effect Start" => args.first().map(|e| {
+            if e.ends_with(".fseq") {
+                PlayerState::Sequence(e.clone())
+            } else {
+                PlayerState::Sequence(format!("{e}.fseq"))
+            }
+        }),
+        "Stop Now"
+        | "Stop Gracefully"
+        | "Stop Gracefully After Loop"
+        | "Effects Stop"
+        | "FSEQ Effect Stop" => Some(PlayerState::Stop),
+        _ => None,
+    }
+}
+
+fn read_cstring<R>(r: &mut R) -> Result<String>
+where
+    R: std::io::Read,
+{
+    let mut data = Vec::new();
+    loop {
+        match r.read_u8()? {
+            0 => break,
+            c => data.push(c),
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&data).to_string())
+}
+
 trait Reader<R>
 where
     R: std::io::Read,
@@ -178,7 +245,7 @@ where
             PacketType::Blanking => PacketBody::Blanking,
             PacketType::Ping => PacketBody::Ping(Ping::read(r)?),
             PacketType::Plugin => PacketBody::Plugin,
-            PacketType::Command => PacketBody::Command,
+            PacketType::Command => PacketBody::Command(Command::read(r)?),
             PacketType::Unknown => bail!("Unknown packet type"),
         };
 
@@ -212,7 +279,7 @@ pub enum PacketBody {
     Blanking,
     Ping(Ping),
     Plugin,
-    Command,
+    Command(Command),
 }
 
 #[derive(Debug, PartialEq)]
@@ -344,6 +411,41 @@ where
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct Command {
+    data_len: u16,
+    command: String,
+    args: Vec<String>,
+}
+
+impl Command {
+    pub fn player_state(&self) -> Option<PlayerState> {
+        player_state(&self.command, &self.args)
+    }
+}
+
+impl<R> Reader<R> for Command
+where
+    R: std::io::Read,
+{
+    fn read(r: &mut R) -> Result<Self> {
+        let data_len = r.read_u16::<LittleEndian>()?;
+        let arg_count = r.read_u8()?;
+        let command = read_cstring(r)?;
+
+        let mut args = Vec::new();
+        for _ in 0..arg_count {
+            args.push(read_cstring(r)?);
+        }
+
+        Ok(Command {
+            data_len,
+            command,
+            args,
+        })
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct OperatingMode {
     bridge: bool,
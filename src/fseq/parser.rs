@@ -27,7 +27,7 @@ pub struct FSeq {
     pub uuid: u64,
 
     compressed_blocks: Vec<CompressedBlock>,
-    sparse_ranges: Vec<SparseRange>,
+    pub sparse_ranges: Vec<SparseRange>,
     pub variables: Vec<Variable>,
 
     cached_first_frame: u32,
@@ -126,9 +126,12 @@ impl std::fmt::Display for CompressedBlock {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct SparseRange {
-    start_channel: u32,
-    end_channel_offset: u32,
+    /// Zero based offset of the first channel in the range
+    pub start_channel: u32,
+    /// Number of channels in the range
+    pub end_channel_offset: u32,
 }
 
 impl std::fmt::Display for SparseRange {
@@ -140,6 +143,7 @@ impl std::fmt::Display for SparseRange {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct Variable {
     pub code: String,
     pub data: String,
@@ -329,14 +333,12 @@ impl FSeq {
         // Read just the specific frame back
         let offset = f - self.cached_first_frame;
         let start = (offset * self.channel_count) as usize;
-        let end = start + self.channel_count as usize;
+        let end = (start + self.channel_count as usize).min(self.cached.len());
         Ok(Some(
             self.cached
-                .iter()
-                .enumerate()
-                .filter(|(i, _x)| start <= *i && *i < end)
-                .map(|(_i, x)| *x)
-                .collect(),
+                .get(start..end)
+                .map(|d| d.to_vec())
+                .unwrap_or_default(),
         ))
     }
 }
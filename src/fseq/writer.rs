@@ -0,0 +1,508 @@
+use std::{
+    io::{Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use byteorder::{BigEndian, LittleEndian, WriteBytesExt};
+
+use crate::fseq::parser::{CompressionType, FSeq, SparseRange, Variable};
+
+/// Length of the fixed part of the header
+const HEADER_LEN: u16 = 32;
+/// The parser only understands the low byte of the block count
+const MAX_BLOCKS: u32 = 255;
+/// Rough size of the uncompressed data in each block
+const BLOCK_BYTES: u32 = 1024 * 1024;
+
+/// Writes a zstd compressed v2 FSEQ file
+///
+/// The number of frames has to be known up front so space can be left
+/// for the compressed block index, which is filled in by `finish` once
+/// the size of every block is known.
+pub struct Writer<W: Write + Seek> {
+    w: W,
+    channel_count: u32,
+    frame_count: u32,
+    frames_per_block: u32,
+    frames_written: u32,
+    block: Vec<u8>,
+    blocks: Vec<(u32, u32)>,
+}
+
+impl<W: Write + Seek> Writer<W> {
+    pub fn new(
+        mut w: W,
+        channel_count: u32,
+        frame_count: u32,
+        step_time_ms: u8,
+        sparse_ranges: &[SparseRange],
+        variables: &[Variable],
+    ) -> Result<Self> {
+        if channel_count == 0 || frame_count == 0 {
+            bail!("Sequences need at least one channel and one frame");
+        }
+
+        let frames_per_block = (BLOCK_BYTES / channel_count)
+            .max(frame_count.div_ceil(MAX_BLOCKS))
+            .max(1);
+        let block_count = frame_count.div_ceil(frames_per_block);
+
+        let variable_data_offset =
+            HEADER_LEN as usize + block_count as usize * 8 + sparse_ranges.len() * 6;
+        let variable_len = variables
+            .iter()
+            .map(|v| 4 + v.data.len() + 1)
+            .sum::<usize>();
+        let channel_data_offset = variable_data_offset + variable_len;
+        if channel_data_offset > u16::MAX as usize {
+            bail!("Sequence header is too large");
+        }
+
+        w.write_u32::<BigEndian>(0x50534551)?;
+        w.write_u16::<LittleEndian>(channel_data_offset as u16)?;
+        w.write_u8(0)?; // Minor version
+        w.write_u8(2)?; // Major version
+        w.write_u16::<LittleEndian>(variable_data_offset as u16)?;
+        w.write_u32::<LittleEndian>(channel_count)?;
+        w.write_u32::<LittleEndian>(frame_count)?;
+        w.write_u8(step_time_ms)?;
+        w.write_u8(0)?; // Flags
+        w.write_u8(u8::from(CompressionType::Zstd))?;
+        w.write_u8(block_count as u8)?;
+        w.write_u8(sparse_ranges.len() as u8)?;
+        w.write_u8(0)?; // Reserved
+        w.write_u64::<LittleEndian>(chrono::Utc::now().timestamp_micros() as u64)?;
+
+        // Filled in once the blocks are written
+        w.write_all(&vec![0; block_count as usize * 8])?;
+
+        for sr in sparse_ranges {
+            w.write_all(&sr.start_channel.to_le_bytes()[..3])?;
+            w.write_all(&sr.end_channel_offset.to_le_bytes()[..3])?;
+        }
+
+        for v in variables {
+            if v.code.len() != 2 {
+                bail!("Invalid variable code '{}'", v.code);
+            }
+            w.write_u16::<LittleEndian>((4 + v.data.len() + 1) as u16)?;
+            w.write_all(v.code.as_bytes())?;
+            w.write_all(v.data.as_bytes())?;
+            w.write_u8(0)?;
+        }
+
+        Ok(Self {
+            w,
+            channel_count,
+            frame_count,
+            frames_per_block,
+            frames_written: 0,
+            block: Vec::new(),
+            blocks: Vec::new(),
+        })
+    }
+
+    /// Add the next frame, which is padded or truncated to the
+    /// channel count
+    pub fn write_frame(&mut self, frame: &[u8]) -> Result<()> {
+        if self.frames_written >= self.frame_count {
+            bail!("Sequence already has {} frames", self.frame_count);
+        }
+
+        let len = self.channel_count as usize;
+        self.block.extend_from_slice(&frame[..frame.len().min(len)]);
+        self.block
+            .resize(self.block.len() + len.saturating_sub(frame.len()), 0);
+        self.frames_written += 1;
+
+        if self.frames_written.is_multiple_of(self.frames_per_block) {
+            self.flush_block()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the remaining data and the block index
+    pub fn finish(mut self) -> Result<W> {
+        if self.frames_written != self.frame_count {
+            bail!(
+                "Sequence should have {} frames but has {}",
+                self.frame_count,
+                self.frames_written
+            );
+        }
+        self.flush_block()?;
+
+        self.w.seek(SeekFrom::Start(HEADER_LEN as u64))?;
+        for (first_frame, size) in self.blocks.iter() {
+            self.w.write_u32::<LittleEndian>(*first_frame)?;
+            self.w.write_u32::<LittleEndian>(*size)?;
+        }
+        self.w.seek(SeekFrom::End(0))?;
+        self.w.flush()?;
+
+        Ok(self.w)
+    }
+
+    fn flush_block(&mut self) -> Result<()> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+
+        let compressed = zstd::encode_all(self.block.as_slice(), 0)?;
+        let frames = (self.block.len() / self.channel_count as usize) as u32;
+        self.blocks
+            .push((self.frames_written - frames, compressed.len() as u32));
+        self.w.write_all(&compressed)?;
+        self.block.clear();
+
+        Ok(())
+    }
+}
+
+/// Copy frames `start..end` of a sequence
+pub fn slice<W: Write + Seek>(seq: &mut FSeq, start: u32, end: u32, w: W) -> Result<W> {
+    if start >= end || end > seq.frame_count {
+        bail!("Invalid frame range {start}-{end}");
+    }
+
+    let mut writer = Writer::new(
+        w,
+        seq.channel_count,
+        end - start,
+        seq.step_time_ms,
+        &seq.sparse_ranges,
+        &seq.variables,
+    )?;
+
+    for f in start..end {
+        let frame = seq
+            .get_frame(f)
+            .with_context(|| format!("Couldn't read frame {f}"))?
+            .unwrap_or_default();
+        writer.write_frame(&frame)?;
+    }
+
+    writer.finish()
+}
+
+/// Check that sequences can be joined, they must have the same
+/// channels and step time
+pub fn check_joinable(seqs: &[FSeq]) -> Result<()> {
+    let Some(first) = seqs.first() else {
+        bail!("No sequences to join");
+    };
+
+    let name = |seq: &FSeq| {
+        Path::new(&seq.filename)
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+
+    for seq in seqs.iter().skip(1) {
+        if seq.channel_count != first.channel_count || seq.sparse_ranges != first.sparse_ranges {
+            bail!("{} has different channels to {}", name(seq), name(first));
+        }
+        if seq.step_time_ms != first.step_time_ms {
+            bail!("{} has a different step time to {}", name(seq), name(first));
+        }
+    }
+
+    Ok(())
+}
+
+/// Join sequences one after another, keeping the variables of the
+/// first sequence
+pub fn concat<W: Write + Seek>(seqs: &mut [FSeq], w: W) -> Result<W> {
+    check_joinable(seqs)?;
+
+    let frame_count = seqs.iter().map(|s| s.frame_count).sum();
+    let first = &seqs[0];
+    let mut writer = Writer::new(
+        w,
+        first.channel_count,
+        frame_count,
+        first.step_time_ms,
+        &first.sparse_ranges,
+        &first.variables,
+    )?;
+
+    for seq in seqs.iter_mut() {
+        for f in 0..seq.frame_count {
+            let frame = seq
+                .get_frame(f)
+                .with_context(|| format!("Couldn't read frame {f}"))?
+                .unwrap_or_default();
+            writer.write_frame(&frame)?;
+        }
+    }
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, path::Path};
+
+    use tempfile::TempDir;
+
+    use crate::fseq::parser;
+
+    use super::*;
+
+    /// Channels which fit one frame in each block
+    const BIG_FRAME: u32 = BLOCK_BYTES / 2 + 1;
+
+    fn variables() -> Vec<Variable> {
+        vec![
+            Variable {
+                code: "sp".into(),
+                data: "xLights".into(),
+            },
+            Variable {
+                code: "mf".into(),
+                data: "show.xsq".into(),
+            },
+        ]
+    }
+
+    fn sparse_ranges() -> Vec<SparseRange> {
+        vec![
+            SparseRange {
+                start_channel: 0,
+                end_channel_offset: 6,
+            },
+            SparseRange {
+                start_channel: 300,
+                end_channel_offset: 6,
+            },
+        ]
+    }
+
+    /// A frame which is different for every frame number
+    fn frame(channels: u32, number: u32) -> Vec<u8> {
+        (0..channels)
+            .map(|c| (number * 7 + c) as u8)
+            .collect::<Vec<_>>()
+    }
+
+    fn write(
+        dir: &TempDir,
+        name: &str,
+        channels: u32,
+        frames: u32,
+        step_time_ms: u8,
+        sparse_ranges: &[SparseRange],
+    ) -> Box<FSeq> {
+        let path = dir.path().join(name);
+        let file = File::create(&path).unwrap();
+        let mut w = Writer::new(
+            file,
+            channels,
+            frames,
+            step_time_ms,
+            sparse_ranges,
+            &variables(),
+        )
+        .unwrap();
+        for f in 0..frames {
+            w.write_frame(&frame(channels, f)).unwrap();
+        }
+        w.finish().unwrap();
+
+        parser::parse(path.to_str().unwrap()).unwrap()
+    }
+
+    fn reopen(path: &Path) -> Box<FSeq> {
+        parser::parse(path.to_str().unwrap()).unwrap()
+    }
+
+    fn frames(seq: &mut FSeq) -> Vec<Vec<u8>> {
+        (0..seq.frame_count)
+            .map(|f| seq.get_frame(f).unwrap().unwrap())
+            .collect()
+    }
+
+    /// The block count as written in the header
+    fn block_count(path: &Path) -> u8 {
+        std::fs::read(path).unwrap()[21]
+    }
+
+    fn vars(variables: &[Variable]) -> Vec<(String, String)> {
+        variables
+            .iter()
+            .map(|v| (v.code.clone(), v.data.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn writer_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut seq = write(&dir, "seq.fseq", 12, 7, 25, &sparse_ranges());
+
+        assert_eq!((seq.major_version, seq.minor_version), (2, 0));
+        assert!(matches!(seq.compression_type, CompressionType::Zstd));
+        assert_eq!(seq.channel_count, 12);
+        assert_eq!(seq.frame_count, 7);
+        assert_eq!(seq.step_time_ms, 25);
+        assert_eq!(seq.sparse_ranges, sparse_ranges());
+        assert_eq!(vars(&seq.variables), vars(&variables()));
+        assert_eq!(
+            frames(&mut seq),
+            (0..7).map(|f| frame(12, f)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn writer_pads_and_truncates_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seq.fseq");
+
+        let mut w = Writer::new(File::create(&path).unwrap(), 4, 2, 50, &[], &[]).unwrap();
+        w.write_frame(&[1, 2]).unwrap();
+        w.write_frame(&[1, 2, 3, 4, 5, 6]).unwrap();
+        assert!(
+            w.write_frame(&[]).is_err(),
+            "wrote more frames than the header"
+        );
+        w.finish().unwrap();
+
+        let mut seq = reopen(&path);
+        assert_eq!(frames(&mut seq), vec![vec![1, 2, 0, 0], vec![1, 2, 3, 4]]);
+    }
+
+    #[test]
+    fn writer_needs_every_frame() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = File::create(dir.path().join("seq.fseq")).unwrap();
+
+        let mut w = Writer::new(file, 4, 2, 50, &[], &[]).unwrap();
+        w.write_frame(&[1]).unwrap();
+        assert!(w.finish().is_err());
+
+        let file = File::create(dir.path().join("empty.fseq")).unwrap();
+        assert!(Writer::new(file, 4, 0, 50, &[], &[]).is_err());
+    }
+
+    #[test]
+    fn writer_splits_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut seq = write(&dir, "seq.fseq", BIG_FRAME, 3, 50, &[]);
+
+        assert_eq!(block_count(&dir.path().join("seq.fseq")), 3);
+        assert_eq!(
+            frames(&mut seq),
+            (0..3).map(|f| frame(BIG_FRAME, f)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn slice_copies_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut seq = write(&dir, "seq.fseq", 12, 7, 25, &sparse_ranges());
+
+        let path = dir.path().join("slice.fseq");
+        slice(&mut seq, 2, 5, File::create(&path).unwrap()).unwrap();
+
+        let mut sliced = reopen(&path);
+        assert_eq!(sliced.channel_count, 12);
+        assert_eq!(sliced.frame_count, 3);
+        assert_eq!(sliced.step_time_ms, 25);
+        assert_eq!(sliced.sparse_ranges, sparse_ranges());
+        assert_eq!(vars(&sliced.variables), vars(&seq.variables));
+        assert_eq!(
+            frames(&mut sliced),
+            (2..5).map(|f| frame(12, f)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn slice_rejects_bad_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut seq = write(&dir, "seq.fseq", 12, 7, 25, &[]);
+
+        for (start, end) in [(3, 3), (5, 2), (0, 8), (7, 8)] {
+            let file = File::create(dir.path().join("slice.fseq")).unwrap();
+            assert!(
+                slice(&mut seq, start, end, file).is_err(),
+                "{start}-{end} was sliced"
+            );
+        }
+    }
+
+    #[test]
+    fn slice_at_the_block_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let frame_count = MAX_BLOCKS + 1;
+        let mut seq = write(&dir, "seq.fseq", BIG_FRAME, frame_count, 50, &[]);
+        // Two frames to a block to stay under the limit
+        assert_eq!(
+            block_count(&dir.path().join("seq.fseq")) as u32,
+            frame_count.div_ceil(2)
+        );
+
+        // One frame to a block, exactly at the limit
+        let path = dir.path().join("limit.fseq");
+        slice(&mut seq, 1, frame_count, File::create(&path).unwrap()).unwrap();
+        assert_eq!(block_count(&path) as u32, MAX_BLOCKS);
+
+        let mut sliced = reopen(&path);
+        assert_eq!(sliced.frame_count, MAX_BLOCKS);
+        for f in [0, 1, MAX_BLOCKS / 2, MAX_BLOCKS - 1] {
+            assert_eq!(
+                sliced.get_frame(f).unwrap().unwrap(),
+                frame(BIG_FRAME, f + 1),
+                "frame {f}"
+            );
+        }
+    }
+
+    #[test]
+    fn concat_joins_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut seqs = [
+            *write(&dir, "a.fseq", 12, 3, 25, &sparse_ranges()),
+            *write(&dir, "b.fseq", 12, 4, 25, &sparse_ranges()),
+        ];
+        let expected = seqs.iter_mut().flat_map(frames).collect::<Vec<_>>();
+
+        let path = dir.path().join("joined.fseq");
+        concat(&mut seqs, File::create(&path).unwrap()).unwrap();
+
+        let mut joined = reopen(&path);
+        assert_eq!(joined.channel_count, 12);
+        assert_eq!(joined.frame_count, 7);
+        assert_eq!(joined.step_time_ms, 25);
+        assert_eq!(joined.sparse_ranges, sparse_ranges());
+        assert_eq!(vars(&joined.variables), vars(&seqs[0].variables));
+        assert_eq!(frames(&mut joined), expected);
+    }
+
+    #[test]
+    fn concat_rejects_mismatches() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = || *write(&dir, "a.fseq", 12, 3, 25, &[]);
+
+        for (name, mut seqs) in [
+            (
+                "channel count",
+                [a(), *write(&dir, "b.fseq", 15, 3, 25, &[])],
+            ),
+            (
+                "sparse ranges",
+                [a(), *write(&dir, "b.fseq", 12, 3, 25, &sparse_ranges())],
+            ),
+            ("step time", [a(), *write(&dir, "b.fseq", 12, 3, 50, &[])]),
+        ] {
+            let path = dir.path().join("joined.fseq");
+            assert!(
+                concat(&mut seqs, File::create(&path).unwrap()).is_err(),
+                "joined with a different {name}"
+            );
+        }
+
+        assert!(check_joinable(&[]).is_err());
+    }
+}
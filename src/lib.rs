@@ -1,3 +1,5 @@
+pub mod auth;
+pub mod bridge;
 pub mod built_info {
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
 }
@@ -5,12 +7,17 @@ pub mod built_info {
 pub mod button;
 pub mod config;
 pub mod db;
+pub mod dimmer;
+pub mod display;
 pub mod error;
 pub mod fpp;
 pub mod fseq;
 pub mod models;
+pub mod output;
+pub mod overlay;
 pub mod patterns;
 pub mod player;
 pub mod state;
 pub mod storage;
+pub mod validate;
 pub mod web;
@@ -1,13 +1,15 @@
 use std::{collections::HashMap, net::Ipv4Addr};
 
-use axum::body::Bytes;
-use axum_typed_multipart::{FieldData, TryFromMultipart};
 use chrono::{Datelike, Timelike};
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::{db, patterns::TestSpec};
+use crate::{
+    db::{self, models::Role},
+    display::Pixel,
+    patterns::{Color, TestSpec},
+};
 
 #[derive(Debug, Default, Serialize, ToSchema)]
 pub struct SystemInfo {
@@ -144,12 +146,80 @@ pub struct SequenceMeta {
     #[schema(example = 10)]
     #[serde(rename = "ChannelCount")]
     pub channel_count: u32,
+    /// SHA-256 of the file when it was uploaded
+    #[schema(example = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")]
+    #[serde(rename = "Checksum")]
+    pub checksum: String,
+    /// Problems found comparing the sequence with the outputs and models
+    #[schema(example = json!(["Sequence is 3 channels short of outputs"]))]
+    #[serde(rename = "Warnings")]
+    pub warnings: Vec<String>,
     /// Any additional variables
     #[schema(example = json!({"sp": "xLights"}))]
     #[serde(rename = "variableHeaders")]
     pub variables: HashMap<String, String>,
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct Credentials {
+    #[schema(example = "admin")]
+    pub username: String,
+    #[schema(example = "password")]
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CurrentUser {
+    /// The user or API token making the request
+    #[schema(example = "admin")]
+    pub username: String,
+    pub role: Role,
+    /// Whether logging in is required
+    #[schema(example = true)]
+    pub auth_enabled: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserDetails {
+    #[schema(example = "admin")]
+    pub username: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UserRequest {
+    #[schema(example = "operator")]
+    pub username: String,
+    #[schema(example = "password")]
+    pub password: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UserUpdate {
+    /// A new password, logging the user out everywhere
+    #[schema(example = "password")]
+    pub password: Option<String>,
+    pub role: Option<Role>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TokenRequest {
+    #[schema(example = "xLights")]
+    pub name: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NewToken {
+    #[schema(example = "xLights")]
+    pub name: String,
+    pub role: Role,
+    /// The token, which can't be retrieved again
+    #[schema(example = "0123456789abcdef")]
+    pub token: String,
+}
+
 #[derive(Debug, Default, Serialize, ToSchema)]
 pub struct Status {
     /// Status
@@ -160,18 +230,240 @@ pub struct Status {
     pub error: Option<String>,
 }
 
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct FppResponse {
+    /// Status
+    #[schema(example = "OK")]
+    #[serde(rename = "Status")]
+    pub status: String,
+    /// Any additional information
+    #[schema(example = "Playlist started")]
+    #[serde(rename = "Message")]
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FppCommand {
+    /// The FPP command to run
+    #[schema(example = "Start Playlist")]
+    pub command: String,
+    /// Arguments to the command
+    #[schema(example = json!(["playlist"]))]
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct FppStatus {
+    /// State of the daemon
+    #[schema(example = "running")]
+    pub fppd: String,
+    /// Mode of the system, 2 is player
+    #[schema(example = 2)]
+    pub mode: u8,
+    /// Name of the mode of the system
+    #[schema(example = "player")]
+    pub mode_name: String,
+    /// Player status, 0 is idle and 1 is playing
+    #[schema(example = 0)]
+    pub status: u8,
+    /// Name of the player status
+    #[schema(example = "idle")]
+    pub status_name: String,
+    /// The playlist currently playing
+    pub current_playlist: FppCurrentPlaylist,
+    /// The sequence currently playing
+    #[schema(example = "sequence.fseq")]
+    pub current_sequence: String,
+    /// The media currently playing
+    #[schema(example = "")]
+    pub current_song: String,
+    /// Seconds into the current sequence
+    #[schema(example = "0")]
+    pub seconds_played: String,
+    /// Seconds remaining of the current sequence
+    #[schema(example = "0")]
+    pub seconds_remaining: String,
+    /// Time into the current sequence
+    #[schema(example = "00:00")]
+    pub time_elapsed: String,
+    /// Time remaining of the current sequence
+    #[schema(example = "00:00")]
+    pub time_remaining: String,
+    /// The next scheduled playlist
+    pub next_playlist: FppNextPlaylist,
+    /// Whether the playlist repeats
+    #[schema(example = "0")]
+    pub repeat_mode: String,
+    /// Whether bridge mode is active
+    #[schema(example = false)]
+    pub bridging: bool,
+    /// Whether multisync is active
+    #[schema(example = false)]
+    pub multisync: bool,
+    /// Audio volume
+    #[schema(example = 0)]
+    pub volume: u8,
+    /// The current system time
+    #[schema(example = "Sun Nov 26 12:49:56 GMT 2023")]
+    pub time: String,
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct FppCurrentPlaylist {
+    /// Number of items in the playlist
+    #[schema(example = "0")]
+    pub count: String,
+    /// Description of the playlist
+    #[schema(example = "")]
+    pub description: String,
+    /// Index of the current item
+    #[schema(example = "0")]
+    pub index: String,
+    /// Name of the playlist
+    #[schema(example = "playlist")]
+    pub playlist: String,
+    /// Type of the current item
+    #[schema(example = "sequence")]
+    #[serde(rename = "type")]
+    pub playlist_type: String,
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct FppNextPlaylist {
+    /// Name of the next playlist
+    #[schema(example = "No playlist scheduled.")]
+    pub playlist: String,
+    /// When the next playlist will start
+    #[schema(example = "")]
+    pub start_time: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct RenderQuery {
+    /// Time into the sequence to start rendering from
+    pub start_ms: Option<u64>,
+    /// Time into the sequence to stop rendering at
+    pub end_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RenderedSequence {
+    /// Milliseconds between each frame
+    #[schema(example = 25)]
+    pub step_ms: u32,
+    /// The number of the first rendered frame
+    #[schema(example = 0)]
+    pub start_frame: u32,
+    /// The pixels of the virtual display
+    pub pixels: Vec<Pixel>,
+    /// The RGB colour of each pixel, three bytes per pixel, for each
+    /// frame, base64 encoded
+    pub frames: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SequenceSort {
+    #[default]
+    Name,
+    Duration,
+    Uploaded,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct SequenceSearch {
+    /// Text to find in the name or variables of the sequence
+    pub q: Option<String>,
+    /// Only include sequences with this tag
+    pub tag: Option<String>,
+    /// What to sort by
+    pub sort: Option<SequenceSort>,
+    /// Sort in descending order
+    pub desc: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SequenceDetails {
+    #[schema(example = "sequence.fseq")]
+    pub name: String,
+    /// Number of channels
+    #[schema(example = 150)]
+    pub channels: u32,
+    /// Number of frames
+    #[schema(example = 1200)]
+    pub frames: u32,
+    /// Length of the sequence in milliseconds
+    #[schema(example = 30000)]
+    pub duration_ms: u64,
+    /// Unix timestamp of when the sequence was uploaded
+    #[schema(example = 1735689600)]
+    pub uploaded: i64,
+    /// Percentage brightness the sequence is played at
+    #[schema(example = 100)]
+    pub brightness: u8,
+    #[schema(example = json!(["christmas"]))]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RenameSequence {
+    /// The new filename
+    #[schema(example = "new_name.fseq")]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CopySequence {
+    /// Filename of the copy
+    #[schema(example = "copy.fseq")]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TrimSequence {
+    /// Filename of the new sequence
+    #[schema(example = "intro.fseq")]
+    pub name: String,
+    /// Time into the sequence to start from
+    #[schema(example = 0)]
+    pub start_ms: Option<u64>,
+    /// Time into the sequence to stop at
+    #[schema(example = 30000)]
+    pub end_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ConcatSequences {
+    /// Filename of the new sequence
+    #[schema(example = "medley.fseq")]
+    pub name: String,
+    /// The sequences to join, in order
+    #[schema(example = json!(["first.fseq", "second.fseq"]))]
+    pub sequences: Vec<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct PreviewQuery {
+    /// Only send the channels of this model
+    pub model: Option<String>,
+    /// The maximum number of frames to send per second
+    pub fps: Option<u32>,
+}
+
 #[derive(Deserialize)]
 pub struct CommandQuery {
     pub command: String,
     pub file: String,
 }
 
-#[derive(TryFromMultipart, ToSchema)]
+/// Only used to document uploads, the files are streamed to disk as
+/// they are received
+#[derive(ToSchema)]
 pub struct FileUpload {
     /// File or files to upload
-    #[form_data(limit = "2MiB")]
     #[schema(value_type = String, format = Binary)]
-    pub myfile: Vec<FieldData<Bytes>>,
+    pub myfile: Vec<u8>,
 }
 
 #[derive(ToSchema)]
@@ -229,6 +521,41 @@ pub struct PlaylistEntry {
     #[schema(example = "sequence")]
     #[serde(rename = "type")]
     pub playlist_type: String,
+    /// Percentage brightness to play the sequence at
+    #[schema(example = 100)]
+    pub brightness: Option<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Brightness {
+    /// Percentage brightness, scaled so 50% looks half as bright
+    #[schema(example = 80, maximum = 100)]
+    pub brightness: u8,
+}
+
+/// Manual control of a model on top of whatever is playing
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Override {
+    /// Light the whole model one colour
+    Solid(Color),
+    /// Turn the model off
+    Off,
+    /// Dim the model to a percentage brightness
+    Brightness(u8),
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RecordSpec {
+    /// Name of the sequence to record to
+    #[schema(example = "recording.fseq")]
+    pub name: String,
+    /// How long to record for
+    #[schema(example = 60000)]
+    pub duration_ms: u64,
+    /// Time between frames, defaults to 50ms
+    #[schema(example = 50)]
+    pub step_ms: Option<u8>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -311,6 +638,36 @@ pub struct Channels {
     pub channel_outputs: Vec<ChannelOutput>,
 }
 
+/// Order a universe's pixels expect their colours in
+///
+/// Sequences are always RGB, the RGBW orders add a white channel to
+/// every pixel taken from the common part of the three colours.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ColorOrder {
+    #[default]
+    Rgb,
+    Rbg,
+    Grb,
+    Gbr,
+    Brg,
+    Bgr,
+    Rgbw,
+    Grbw,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct OutputSettings {
+    /// ID of the universe the settings apply to
+    #[schema(example = 1)]
+    pub id: u32,
+    #[serde(default)]
+    pub color_order: ColorOrder,
+    /// Gamma correction to apply, none sends values unchanged
+    #[schema(example = 2.2)]
+    pub gamma: Option<f32>,
+}
+
 #[derive(Debug, PartialEq, Serialize, Clone, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum PlayerState {
@@ -318,19 +675,97 @@ pub enum PlayerState {
     Playlist(String),
     Sequence(String),
     Test(TestSpec),
+    Bridge,
     Stop,
 }
 
-#[derive(Debug, PartialEq, Serialize, Clone, ToSchema)]
+#[derive(Debug, Default, PartialEq, Serialize, Clone, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum PlayerStatus {
     Scheduler,
     Playlist,
     Sequence,
     Testing,
+    Bridge,
+    #[default]
     Stopped,
 }
 
+#[derive(Debug, Default, PartialEq, Serialize, Clone, ToSchema)]
+pub struct PlayerInfo {
+    /// The mode of the player
+    pub status: PlayerStatus,
+    /// The schedule currently running
+    #[schema(example = "Schedule")]
+    pub schedule: Option<String>,
+    /// The playlist currently playing
+    #[schema(example = "playlist")]
+    pub playlist: Option<String>,
+    /// The sequence currently playing
+    #[schema(example = "sequence.fseq")]
+    pub sequence: Option<String>,
+    /// Index of the current sequence within the playlist
+    #[schema(example = 0)]
+    pub playlist_index: u32,
+    /// The number of sequences in the playlist
+    #[schema(example = 3)]
+    pub playlist_count: u32,
+    /// The current loop through the playlist
+    #[schema(example = 0)]
+    pub loop_count: u32,
+    /// The current frame of the sequence
+    #[schema(example = 100)]
+    pub frame: u32,
+    /// The number of frames in the sequence
+    #[schema(example = 1000)]
+    pub frames: u32,
+    /// Milliseconds into the sequence
+    #[schema(example = 2500)]
+    pub elapsed_ms: u64,
+    /// Milliseconds remaining of the sequence
+    #[schema(example = 22500)]
+    pub remaining_ms: u64,
+    /// The health of each controller being sent to
+    pub senders: Vec<SenderStatus>,
+    /// The next schedule due to start
+    pub next_schedule: Option<UpcomingSchedule>,
+    /// The most recent error reported by the player
+    #[schema(example = "Playlist not found: playlist")]
+    pub last_error: Option<String>,
+    /// The sequence the output is being recorded to
+    #[schema(example = "recording.fseq")]
+    pub recording: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone, ToSchema)]
+pub struct UpcomingSchedule {
+    /// The name of the schedule
+    #[schema(example = "Schedule")]
+    pub schedule: String,
+    /// The playlist the schedule will play
+    #[schema(example = "playlist")]
+    pub playlist: String,
+    /// When the schedule starts
+    #[schema(example = "2024-12-01T17:00:00", format = "date-time")]
+    pub start: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone, ToSchema)]
+pub struct SenderStatus {
+    /// The address of the controller
+    #[schema(value_type = String, format = "ipv4")]
+    pub address: Ipv4Addr,
+    /// Whether the last frame was sent successfully
+    #[schema(example = true)]
+    pub healthy: bool,
+    /// How many times connecting or sending to the controller failed
+    #[schema(example = 0)]
+    pub errors: u64,
+    /// The most recent error
+    #[schema(example = "Network is unreachable (os error 101)")]
+    pub last_error: Option<String>,
+}
+
 #[derive(Debug, PartialEq, Serialize, Clone, ToSchema)]
 pub struct NumberedPlaylist {
     #[schema(example = "name")]
@@ -0,0 +1,264 @@
+use crate::models::{ColorOrder, OutputSettings};
+
+/// Converts RGB channel data into what a universe's pixels expect
+pub struct Transform {
+    order: ColorOrder,
+    gamma: Option<[u8; 256]>,
+}
+
+impl Transform {
+    pub fn new(settings: Option<&OutputSettings>) -> Self {
+        let order = settings.map(|s| s.color_order).unwrap_or_default();
+        let gamma = settings
+            .and_then(|s| s.gamma)
+            .filter(|g| *g != 1.0)
+            .map(|g| std::array::from_fn(|i| ((i as f32 / 255.0).powf(g) * 255.0).round() as u8));
+
+        Self { order, gamma }
+    }
+
+    /// Apply gamma correction and reorder the colours of every pixel
+    ///
+    /// Any channels left over after the last whole pixel are sent as
+    /// they are.
+    pub fn apply(&self, mut data: Vec<u8>) -> Vec<u8> {
+        if let Some(lut) = &self.gamma {
+            for v in data.iter_mut() {
+                *v = lut[*v as usize];
+            }
+        }
+
+        let order: [usize; 3] = match self.order {
+            ColorOrder::Rgb => return data,
+            ColorOrder::Rbg => [0, 2, 1],
+            ColorOrder::Grb => [1, 0, 2],
+            ColorOrder::Gbr => [1, 2, 0],
+            ColorOrder::Brg => [2, 0, 1],
+            ColorOrder::Bgr => [2, 1, 0],
+            ColorOrder::Rgbw => return to_rgbw(&data, [0, 1, 2]),
+            ColorOrder::Grbw => return to_rgbw(&data, [1, 0, 2]),
+        };
+
+        for px in data.chunks_exact_mut(3) {
+            let rgb = [px[0], px[1], px[2]];
+            for (c, i) in px.iter_mut().zip(order) {
+                *c = rgb[i];
+            }
+        }
+
+        data
+    }
+}
+
+/// Expand every pixel to four channels, moving the colour common to
+/// red, green and blue to the white channel
+fn to_rgbw(data: &[u8], order: [usize; 3]) -> Vec<u8> {
+    let pixels = data.chunks_exact(3);
+    let rest = pixels.remainder();
+
+    let mut out = Vec::with_capacity(data.len() / 3 * 4 + rest.len());
+    for px in pixels {
+        let w = px[0].min(px[1]).min(px[2]);
+        out.extend(order.iter().map(|i| px[*i] - w));
+        out.push(w);
+    }
+    out.extend_from_slice(rest);
+
+    out
+}
+
+/// Part of an output's channels
+#[derive(Debug, PartialEq)]
+pub struct Target {
+    /// Index of the output
+    pub output: usize,
+    /// Position of the channels within the output
+    pub offset: usize,
+    /// Position of the channels within the data being mapped
+    pub range: std::ops::Range<usize>,
+}
+
+/// Channels which all go to the same outputs
+#[derive(Debug)]
+struct Segment {
+    start: usize,
+    end: usize,
+    /// Each output and the position of `start` within it
+    outputs: Vec<(usize, usize)>,
+}
+
+/// Maps zero based absolute channels to the outputs they are sent to
+///
+/// Built once from the outputs so mapping a frame doesn't have to
+/// search every output. Outputs may overlap, in which case the
+/// channels are sent to all of them, and channels in the gaps between
+/// outputs aren't sent anywhere.
+#[derive(Debug, Default)]
+pub struct ChannelMap {
+    segments: Vec<Segment>,
+}
+
+impl ChannelMap {
+    /// Build a map from the first channel and channel count of every
+    /// output
+    pub fn new(outputs: &[(usize, usize)]) -> Self {
+        let mut bounds = outputs
+            .iter()
+            .flat_map(|(start, len)| [*start, start + len])
+            .collect::<Vec<_>>();
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        let segments = bounds
+            .windows(2)
+            .filter_map(|w| {
+                let (start, end) = (w[0], w[1]);
+                let outputs = outputs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (s, len))| *s <= start && end <= s + len)
+                    .map(|(i, (s, _))| (i, start - s))
+                    .collect::<Vec<_>>();
+
+                (!outputs.is_empty()).then_some(Segment {
+                    start,
+                    end,
+                    outputs,
+                })
+            })
+            .collect();
+
+        Self { segments }
+    }
+
+    /// Find where `len` channels starting at `offset` are sent
+    pub fn map(&self, offset: usize, len: usize) -> impl Iterator<Item = Target> + '_ {
+        let end = offset + len;
+        let first = match len {
+            0 => self.segments.len(),
+            _ => self.segments.partition_point(|s| s.end <= offset),
+        };
+
+        self.segments[first..]
+            .iter()
+            .take_while(move |s| s.start < end)
+            .flat_map(move |s| {
+                let lo = s.start.max(offset);
+                let hi = s.end.min(end);
+
+                s.outputs.iter().map(move |(output, o)| Target {
+                    output: *output,
+                    offset: o + lo - s.start,
+                    range: lo - offset..hi - offset,
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Range = std::ops::Range<usize>;
+
+    /// Every target as (output, offset within the output, range of the
+    /// data), sorted so the order segments are visited in doesn't matter
+    fn targets(map: &ChannelMap, offset: usize, len: usize) -> Vec<(usize, usize, Range)> {
+        let mut t = map
+            .map(offset, len)
+            .map(|t| (t.output, t.offset, t.range))
+            .collect::<Vec<_>>();
+        t.sort_by_key(|(output, _, range)| (*output, range.start));
+        t
+    }
+
+    #[test]
+    fn whole_outputs() {
+        let map = ChannelMap::new(&[(0, 10), (10, 10)]);
+
+        assert_eq!(targets(&map, 0, 20), vec![(0, 0, 0..10), (1, 0, 10..20)]);
+    }
+
+    #[test]
+    fn overlapping_outputs() {
+        let map = ChannelMap::new(&[(0, 10), (5, 10)]);
+
+        assert_eq!(
+            targets(&map, 0, 15),
+            vec![(0, 0, 0..5), (0, 5, 5..10), (1, 0, 5..10), (1, 5, 10..15)]
+        );
+        // The shared channels go to both outputs
+        assert_eq!(targets(&map, 6, 2), vec![(0, 6, 0..2), (1, 1, 0..2)]);
+    }
+
+    #[test]
+    fn identical_outputs() {
+        let map = ChannelMap::new(&[(3, 6), (3, 6)]);
+
+        assert_eq!(targets(&map, 0, 12), vec![(0, 0, 3..9), (1, 0, 3..9)]);
+    }
+
+    #[test]
+    fn starts_in_a_gap() {
+        let map = ChannelMap::new(&[(0, 10), (20, 10)]);
+
+        assert_eq!(targets(&map, 15, 10), vec![(1, 0, 5..10)]);
+        assert_eq!(targets(&map, 12, 5), vec![]);
+    }
+
+    #[test]
+    fn spans_a_gap() {
+        let map = ChannelMap::new(&[(0, 10), (20, 10)]);
+
+        assert_eq!(targets(&map, 5, 20), vec![(0, 5, 0..5), (1, 0, 15..20)]);
+    }
+
+    #[test]
+    fn before_and_after_every_output() {
+        let map = ChannelMap::new(&[(10, 10)]);
+
+        assert_eq!(targets(&map, 0, 10), vec![]);
+        assert_eq!(targets(&map, 20, 10), vec![]);
+        assert_eq!(targets(&map, 0, 30), vec![(0, 0, 10..20)]);
+    }
+
+    #[test]
+    fn partial_update_inside_an_output() {
+        let map = ChannelMap::new(&[(0, 10), (10, 100)]);
+
+        assert_eq!(targets(&map, 40, 6), vec![(1, 30, 0..6)]);
+        assert_eq!(targets(&map, 8, 4), vec![(0, 8, 0..2), (1, 0, 2..4)]);
+    }
+
+    #[test]
+    fn zero_length_outputs() {
+        let map = ChannelMap::new(&[(0, 0), (0, 10), (5, 0), (10, 0)]);
+
+        assert_eq!(targets(&map, 0, 10), vec![(1, 0, 0..5), (1, 5, 5..10)]);
+        assert_eq!(targets(&map, 10, 5), vec![]);
+    }
+
+    #[test]
+    fn zero_length_data() {
+        let map = ChannelMap::new(&[(0, 10)]);
+
+        assert_eq!(targets(&map, 5, 0), vec![]);
+    }
+
+    #[test]
+    fn adjacent_outputs_share_a_boundary() {
+        let map = ChannelMap::new(&[(0, 10), (10, 10), (20, 10)]);
+
+        // The last channel of one and the first of the next
+        assert_eq!(targets(&map, 9, 2), vec![(0, 9, 0..1), (1, 0, 1..2)]);
+        assert_eq!(targets(&map, 10, 1), vec![(1, 0, 0..1)]);
+        assert_eq!(targets(&map, 19, 2), vec![(1, 9, 0..1), (2, 0, 1..2)]);
+    }
+
+    #[test]
+    fn no_outputs() {
+        let map = ChannelMap::new(&[]);
+
+        assert_eq!(targets(&map, 0, 10), vec![]);
+    }
+}
@@ -0,0 +1,107 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use crate::{
+    dimmer::Dimmer,
+    models::{Model, Override},
+    output::ChannelMap,
+};
+
+/// Name of the setting holding the model overrides
+pub const SETTING: &str = "overrides";
+
+/// What to do to part of an output
+enum Action {
+    /// Channels of a node, starting from the first channel of the range
+    Fill(Vec<u8>),
+    Dim(Box<Dimmer>),
+}
+
+/// Model overrides resolved to the channels of each output
+///
+/// Overrides are applied to a copy of each frame as it is sent, so
+/// the channels go back to whatever is playing once cleared.
+#[derive(Default)]
+pub struct Overlay {
+    outputs: Vec<Vec<(Range<usize>, Action)>>,
+    /// The master brightness, which the players have already applied
+    /// to everything but the filled channels
+    master: Dimmer,
+}
+
+impl Overlay {
+    pub fn new(
+        overrides: &BTreeMap<String, Override>,
+        models: &[Model],
+        map: &ChannelMap,
+        outputs: usize,
+    ) -> Self {
+        let mut overlay: Vec<Vec<(Range<usize>, Action)>> =
+            (0..outputs).map(|_| Vec::new()).collect();
+
+        for (name, o) in overrides.iter() {
+            let Some(model) = models.iter().find(|m| &m.name == name) else {
+                tracing::warn!("Override for unknown model {name}");
+                continue;
+            };
+
+            let node = match o {
+                Override::Solid(color) => {
+                    color.to_node(model.channel_count_per_node.max(1) as usize)
+                }
+                Override::Off => vec![0],
+                Override::Brightness(_) => Vec::new(),
+            };
+
+            let start = model.start_channel.saturating_sub(1) as usize;
+            for t in map.map(start, model.channel_count as usize) {
+                let action = match o {
+                    Override::Brightness(b) => Action::Dim(Box::new(Dimmer::new(*b))),
+                    // Start from the node channel the range begins on
+                    _ => {
+                        let mut node = node.clone();
+                        let phase = t.range.start % node.len();
+                        node.rotate_left(phase);
+                        Action::Fill(node)
+                    }
+                };
+
+                if let Some(o) = overlay.get_mut(t.output) {
+                    o.push((t.offset..t.offset + t.range.len(), action));
+                }
+            }
+        }
+
+        Self {
+            outputs: overlay,
+            master: Dimmer::default(),
+        }
+    }
+
+    /// Set the master brightness filled channels are dimmed to
+    pub fn set_master(&mut self, percent: u8) {
+        self.master.set(percent);
+    }
+
+    /// Apply the overrides to a frame of an output
+    pub fn apply(&self, output: usize, data: &mut [u8]) {
+        let Some(overrides) = self.outputs.get(output) else {
+            return;
+        };
+
+        for (range, action) in overrides.iter() {
+            let Some(data) = data.get_mut(range.clone()) else {
+                continue;
+            };
+
+            match action {
+                Action::Fill(node) => {
+                    for (v, n) in data.iter_mut().zip(node.iter().cycle()) {
+                        *v = *n;
+                    }
+                    self.master.apply(data);
+                }
+                Action::Dim(dimmer) => dimmer.apply(data),
+            }
+        }
+    }
+}
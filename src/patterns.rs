@@ -4,6 +4,8 @@ use colorgrad::Gradient;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::models::Model;
+
 #[derive(Debug, PartialEq, Serialize, Clone, ToSchema, Deserialize)]
 pub struct Color {
     pub r: u8,
@@ -11,12 +13,43 @@ pub struct Color {
     pub b: u8,
 }
 
+impl Color {
+    /// The channels of a node lit this colour
+    ///
+    /// RGBW nodes move the colour common to red, green and blue to the
+    /// white channel and single channel nodes use its luminance.
+    /// Anything else gets red, green and blue followed by zeros.
+    pub fn to_node(&self, channels: usize) -> Vec<u8> {
+        let (r, g, b) = (self.r, self.g, self.b);
+
+        let mut node = match channels {
+            1 => vec![(0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32).round() as u8],
+            4 => {
+                let w = r.min(g).min(b);
+                vec![r - w, g - w, b - w, w]
+            }
+            _ => vec![r, g, b],
+        };
+        node.resize(channels, 0);
+
+        node
+    }
+}
+
 #[derive(Debug, PartialEq, Serialize, Clone, ToSchema, Deserialize)]
 pub struct Chase {
     pub color: Color,
     pub width: usize,
 }
 
+#[derive(Debug, PartialEq, Serialize, Clone, ToSchema, Deserialize)]
+pub struct Node {
+    pub color: Color,
+    /// Number of the node, counting from 1 as xLights does
+    #[schema(example = 1)]
+    pub number: usize,
+}
+
 #[derive(Debug, PartialEq, Serialize, Clone, ToSchema, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Pattern {
@@ -52,12 +85,125 @@ pub enum Sequence {
     MovingPattern(Pattern),
     CustomPattern(Vec<Color>),
     CustomMovingPattern(Vec<Color>),
+    /// A column of the model sweeping from left to right
+    HorizontalSweep(Color),
+    /// A row of the model sweeping from top to bottom
+    VerticalSweep(Color),
+    /// Each string of the model coloured from the pattern
+    Strings(Pattern),
+    /// The corners of the model lit red (top left), green (top right),
+    /// blue (bottom left) and white (bottom right)
+    Corners,
+    /// Only the given node lit
+    Node(Node),
+    /// Each node flashing its number in binary
+    ///
+    /// Every node is lit blue to mark the start, then off, then shows
+    /// a bit of its number per step, most significant first, green for
+    /// a 1 and red for a 0. Numbers count from 1 as xLights does.
+    BinaryNodes,
+}
+
+/// Where each node of a model sits when laid out as a grid
+///
+/// Models are assumed to be wired as xLights lays out matrices, each
+/// strand running from the start corner along the orientation and
+/// zig-zagging back on the next strand.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub width: usize,
+    pub height: usize,
+    /// The column and row, from the top left, of each node
+    pub nodes: Vec<(usize, usize)>,
+    /// The string of each node
+    pub strings: Vec<usize>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Clone, ToSchema, Deserialize)]
 pub struct TestSpec {
     pub tests: HashMap<String, Sequence>,
     pub step_ms: u64,
+    /// Stop testing after this many milliseconds, otherwise test until
+    /// stopped. Either way the scheduler starts again if it was running
+    #[schema(example = 300000)]
+    pub duration_ms: Option<u64>,
+}
+
+impl Layout {
+    /// A single line of `len` nodes
+    pub fn line(len: usize) -> Self {
+        Self {
+            width: len,
+            height: 1,
+            nodes: (0..len).map(|i| (i, 0)).collect(),
+            strings: vec![0; len],
+        }
+    }
+
+    /// Lay out `len` nodes as the model's strings and strands
+    pub fn new(model: &Model, len: usize) -> Self {
+        if len == 0 {
+            return Self::line(0);
+        }
+
+        let strings = (model.string_count as usize).clamp(1, len);
+        let strands = (strings * model.strands_per_string.max(1) as usize).min(len);
+        let per_string = len.div_ceil(strings);
+        let per_strand = len.div_ceil(strands);
+        // Fewer strands may be needed once they're filled, e.g. 9 nodes
+        // on 4 strands only fill 3 strands of 3
+        let strands = len.div_ceil(per_strand);
+
+        let vertical = model.orientation.eq_ignore_ascii_case("vertical");
+        let corner = model.start_corner.to_ascii_uppercase();
+        let from_bottom = corner.starts_with('B');
+        let from_right = corner.ends_with('R');
+
+        let (width, height) = if vertical {
+            (strands, per_strand)
+        } else {
+            (per_strand, strands)
+        };
+
+        let nodes = (0..len)
+            .map(|i| {
+                let strand = i / per_strand;
+                let mut along = i % per_strand;
+                if strand % 2 == 1 {
+                    along = per_strand - 1 - along;
+                }
+
+                let (mut x, mut y) = if vertical {
+                    (strand, along)
+                } else {
+                    (along, strand)
+                };
+                if from_right {
+                    x = width - 1 - x;
+                }
+                if from_bottom {
+                    y = height - 1 - y;
+                }
+
+                (x, y)
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            nodes,
+            strings: (0..len).map(|i| i / per_string).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
 }
 
 impl Pattern {
@@ -86,72 +232,228 @@ impl Pattern {
 }
 
 impl Sequence {
-    pub fn as_vec(&self, len: usize) -> Vec<u8> {
-        let mut data = Vec::<u8>::new();
+    /// The colour of each node of `layout` at `step` of the test
+    pub fn colors(&self, layout: &Layout, step: usize) -> Vec<Color> {
+        let len = layout.len();
+        let black = Color { r: 0, g: 0, b: 0 };
+        let mut colors = Vec::new();
 
         match self {
             Sequence::Solid(c) => {
-                for _ in 0..len {
-                    data.push(c.r);
-                    data.push(c.g);
-                    data.push(c.b);
-                }
+                colors.resize(len, c.clone());
             }
             Sequence::Chase(c) => {
                 let w = if c.width < len { c.width } else { len };
 
-                for _ in 0..w {
-                    data.push(c.color.r);
-                    data.push(c.color.g);
-                    data.push(c.color.b);
-                }
-
-                for _ in w..len {
-                    data.push(0);
-                    data.push(0);
-                    data.push(0);
-                }
+                colors.resize(w, c.color.clone());
+                colors.resize(len, black);
             }
             Sequence::Pattern(p) | Sequence::MovingPattern(p) => {
                 let grad = p.as_gradient();
 
                 for c in grad.colors(len) {
                     let [r, g, b, _] = c.to_rgba8();
-                    data.push(r);
-                    data.push(g);
-                    data.push(b);
+                    colors.push(Color { r, g, b });
                 }
             }
-            Sequence::CustomPattern(colors) | Self::CustomMovingPattern(colors) => {
-                let colors = colors
+            Sequence::CustomPattern(custom) | Self::CustomMovingPattern(custom) => {
+                let custom = custom
                     .iter()
                     .map(|c| colorgrad::Color::from_linear_rgba8(c.r, c.g, c.b, 255))
                     .collect::<Vec<_>>();
                 let grad = colorgrad::GradientBuilder::new()
-                    .colors(&colors)
+                    .colors(&custom)
                     .build::<colorgrad::BasisGradient>();
                 if let Ok(grad) = grad {
                     for c in grad.colors(len) {
                         let [r, g, b, _] = c.to_rgba8();
-                        data.push(r);
-                        data.push(g);
-                        data.push(b);
+                        colors.push(Color { r, g, b });
                     }
                 } else {
                     tracing::error!("Couldn't build gradient");
                 }
             }
+            Sequence::HorizontalSweep(c) => {
+                let column = step % layout.width.max(1);
+                for (x, _) in layout.nodes.iter() {
+                    colors.push(if *x == column {
+                        c.clone()
+                    } else {
+                        black.clone()
+                    });
+                }
+            }
+            Sequence::VerticalSweep(c) => {
+                let row = step % layout.height.max(1);
+                for (_, y) in layout.nodes.iter() {
+                    colors.push(if *y == row { c.clone() } else { black.clone() });
+                }
+            }
+            Sequence::Strings(p) => {
+                let count = layout.strings.last().map(|s| s + 1).unwrap_or(0);
+                let palette = p
+                    .as_gradient()
+                    .colors(count)
+                    .iter()
+                    .map(|c| {
+                        let [r, g, b, _] = c.to_rgba8();
+                        Color { r, g, b }
+                    })
+                    .collect::<Vec<_>>();
+
+                for s in layout.strings.iter() {
+                    colors.push(palette[*s].clone());
+                }
+            }
+            Sequence::Corners => {
+                // Big enough to spot on a large matrix
+                let size = (layout.width.min(layout.height) / 4).max(1);
+                let right = layout.width.saturating_sub(size);
+                let bottom = layout.height.saturating_sub(size);
+
+                for (x, y) in layout.nodes.iter() {
+                    let c = match (*x < size, *x >= right, *y < size, *y >= bottom) {
+                        (true, _, true, _) => Color { r: 255, g: 0, b: 0 },
+                        (_, true, true, _) => Color { r: 0, g: 255, b: 0 },
+                        (true, _, _, true) => Color { r: 0, g: 0, b: 255 },
+                        (_, true, _, true) => Color {
+                            r: 255,
+                            g: 255,
+                            b: 255,
+                        },
+                        _ => black.clone(),
+                    };
+                    colors.push(c);
+                }
+            }
+            Sequence::Node(n) => {
+                colors.resize(len, black.clone());
+                if let Some(c) = n.number.checked_sub(1).and_then(|i| colors.get_mut(i)) {
+                    *c = n.color.clone();
+                }
+            }
+            Sequence::BinaryNodes => {
+                let bits = (usize::BITS - len.leading_zeros()).max(1) as usize;
+
+                for number in 1..=len {
+                    let c = match step % (bits + 2) {
+                        0 => Color { r: 0, g: 0, b: 255 },
+                        1 => black.clone(),
+                        bit if number >> (bits + 1 - bit) & 1 == 1 => Color { r: 0, g: 255, b: 0 },
+                        _ => Color { r: 255, g: 0, b: 0 },
+                    };
+                    colors.push(c);
+                }
+            }
         }
 
-        data
+        // Sweeps and node numbers move by themselves rather than along
+        // the nodes
+        let rotates = self.moves()
+            && !matches!(
+                self,
+                Sequence::HorizontalSweep(_) | Sequence::VerticalSweep(_) | Sequence::BinaryNodes
+            );
+        if rotates && !colors.is_empty() {
+            let len = colors.len();
+            colors.rotate_right(step % len);
+        }
+
+        colors
+    }
+
+    /// The channel data for each node of `layout` at `step` of the
+    /// test, `channels` channels per node
+    pub fn as_vec(&self, layout: &Layout, step: usize, channels: usize) -> Vec<u8> {
+        self.colors(layout, step)
+            .iter()
+            .flat_map(|c| c.to_node(channels))
+            .collect()
     }
 
     pub fn moves(&self) -> bool {
         match self {
-            Sequence::Solid(_) | Sequence::Pattern(_) | Sequence::CustomPattern(_) => false,
-            Sequence::Chase(_) | Sequence::MovingPattern(_) | Sequence::CustomMovingPattern(_) => {
-                true
-            }
+            Sequence::Solid(_)
+            | Sequence::Pattern(_)
+            | Sequence::CustomPattern(_)
+            | Sequence::Strings(_)
+            | Sequence::Corners
+            | Sequence::Node(_) => false,
+            Sequence::Chase(_)
+            | Sequence::MovingPattern(_)
+            | Sequence::CustomMovingPattern(_)
+            | Sequence::HorizontalSweep(_)
+            | Sequence::VerticalSweep(_)
+            | Sequence::BinaryNodes => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color { r: 255, g: 0, b: 0 };
+    const BLACK: Color = Color { r: 0, g: 0, b: 0 };
+
+    fn model(strings: u32, orientation: &str) -> Model {
+        Model {
+            name: "m".into(),
+            x_lights: true,
+            channel_count: 0,
+            orientation: orientation.into(),
+            start_channel: 1,
+            string_count: strings,
+            channel_count_per_node: 3,
+            strands_per_string: 1,
+            start_corner: "TL".into(),
+            model_type: "Channel".into(),
+        }
+    }
+
+    #[test]
+    fn layout_only_counts_filled_strands() {
+        let layout = Layout::new(&model(4, "horizontal"), 9);
+        assert_eq!((layout.width, layout.height), (3, 3));
+        assert!(layout.nodes.iter().all(|(x, y)| *x < 3 && *y < 3));
+
+        let layout = Layout::new(&model(4, "vertical"), 9);
+        assert_eq!((layout.width, layout.height), (3, 3));
+
+        // Every row of the sweep lights something
+        let sweep = Sequence::VerticalSweep(RED);
+        for step in 0..layout.height {
+            assert!(
+                sweep.colors(&layout, step).contains(&RED),
+                "row {step} is dark"
+            );
         }
     }
+
+    #[test]
+    fn moving_patterns_rotate() {
+        let layout = Layout::line(4);
+        let chase = Sequence::Chase(Chase {
+            color: RED,
+            width: 1,
+        });
+
+        assert_eq!(chase.colors(&layout, 0), vec![RED, BLACK, BLACK, BLACK]);
+        assert_eq!(chase.colors(&layout, 1), vec![BLACK, RED, BLACK, BLACK]);
+        assert_eq!(chase.colors(&layout, 5), chase.colors(&layout, 1));
+    }
+
+    #[test]
+    fn sweeps_dont_rotate() {
+        let layout = Layout::new(&model(2, "horizontal"), 4);
+        let sweep = Sequence::HorizontalSweep(RED);
+
+        // TL zig-zag: (0, 0), (1, 0), (1, 1), (0, 1)
+        assert_eq!(sweep.colors(&layout, 0), vec![RED, BLACK, BLACK, RED]);
+        assert_eq!(sweep.colors(&layout, 1), vec![BLACK, RED, RED, BLACK]);
+
+        let binary = Sequence::BinaryNodes;
+        let first = binary.colors(&layout, 0);
+        assert!(first.iter().all(|c| *c == first[0]));
+    }
 }
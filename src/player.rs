@@ -1,30 +1,102 @@
 use core::time;
-use std::{cmp::Ordering, collections::HashMap, net::Ipv4Addr, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::Ipv4Addr,
+    ops::Range,
+    sync::Arc,
+};
 
 use anyhow::{anyhow, Context, Result};
 use chrono::NaiveTime;
 use ddp_rs::{connection, protocol};
 use parking_lot::Mutex;
-use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::{
+    broadcast,
+    mpsc::{self, Receiver, Sender},
+    watch,
+};
 use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 use crate::{
-    db::{self, models::NextSchedule},
-    models::{PlayerState, PlayerStatus},
+    bridge,
+    db::{
+        self,
+        models::{NextSchedule, Playlist, SequencePlus},
+    },
+    dimmer::{self, Dimmer},
+    models::{
+        Model, Override, PlayerInfo, PlayerState, PlayerStatus, SenderStatus, UpcomingSchedule,
+    },
+    output::{ChannelMap, Transform},
+    overlay::Overlay,
     patterns,
     state::State,
-    storage,
+    storage::{self, StoragePath, StorageType},
 };
 
-struct Data {
-    offset: usize,
-    data: Vec<u8>,
+/// Channel data starting at a zero based channel offset
+#[derive(Debug, Clone)]
+pub struct Data {
+    pub offset: usize,
+    pub data: Vec<u8>,
 }
 
-struct SenderConfig {
+/// Delay before reconnecting to a controller after the first failure
+const RETRY_MIN: time::Duration = time::Duration::from_millis(250);
+/// Longest delay between attempts to reconnect to a controller
+const RETRY_MAX: time::Duration = time::Duration::from_secs(30);
+
+/// A universe sent to a controller
+///
+/// If the connection can't be made or a frame can't be sent the
+/// controller is marked unhealthy and frames are dropped until it's
+/// time to reconnect, waiting twice as long after each failure.
+struct Output<C = connection::DDPConnection> {
+    /// Position in the player's list of senders
+    idx: usize,
+    address: Ipv4Addr,
     offset: usize,
     len: usize,
-    chan: Sender<Data>,
+    /// The last channel data sent, as frames may only update some of it
+    frame: Vec<u8>,
+    transform: Transform,
+    conn: Option<C>,
+    backoff: time::Duration,
+    retry_at: tokio::time::Instant,
+}
+
+/// Opens connections to controllers
+trait Connector {
+    type Conn: Connection;
+
+    fn connect(&self, address: Ipv4Addr) -> Result<Self::Conn>;
+}
+
+/// A connection to a controller
+trait Connection {
+    fn write(&mut self, data: &[u8]) -> Result<()>;
+}
+
+impl Connector for std::net::UdpSocket {
+    type Conn = connection::DDPConnection;
+
+    fn connect(&self, address: Ipv4Addr) -> Result<Self::Conn> {
+        connection::DDPConnection::try_new(
+            format!("{address}:4048"),
+            protocol::PixelConfig::default(),
+            protocol::ID::Default,
+            self.try_clone().context("Failed to share output socket")?,
+        )
+        .context("Failed to create DDP connection")
+    }
+}
+
+impl Connection for connection::DDPConnection {
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        connection::DDPConnection::write(self, data)
+            .map(|_| ())
+            .map_err(|e| anyhow!("{e}"))
+    }
 }
 
 pub async fn controller(
@@ -39,13 +111,51 @@ pub async fn controller(
         }
     }
 
+    // Whether the scheduler was running before testing started, and
+    // whether a test is running and when it should finish if it's timed
+    let mut scheduled = auto_start;
+    let mut testing = false;
+    let mut test_end: Option<tokio::time::Instant> = None;
+
     loop {
+        let until = test_end.unwrap_or_else(tokio::time::Instant::now);
+
         tokio::select! {
             _ = cancel.cancelled() => {
                 return;
             },
+            _ = tokio::time::sleep_until(until), if test_end.is_some() => {
+                test_end = None;
+                testing = false;
+
+                tracing::info!("Test finished");
+                if let Err(e) = next_state.send(PlayerState::Stop).await {
+                    tracing::error!("Could not stop tests: {e}");
+                }
+                if scheduled {
+                    if let Err(e) = next_state.send(PlayerState::Schedule).await {
+                        tracing::error!("Could not restart scheduler: {e}");
+                    }
+                }
+            }
             s = player_ctrl.recv() => {
                 if let Some(s) = s {
+                    test_end = None;
+                    let was_testing = std::mem::take(&mut testing);
+                    match &s {
+                        PlayerState::Test(t) => {
+                            testing = true;
+                            test_end = t.duration_ms.map(|d| {
+                                tokio::time::Instant::now() + time::Duration::from_millis(d)
+                            });
+                        }
+                        PlayerState::Schedule => scheduled = true,
+                        // Stopping a test goes back to the scheduler
+                        PlayerState::Stop if was_testing => {}
+                        _ => scheduled = false,
+                    }
+                    let resume = s == PlayerState::Stop && was_testing && scheduled;
+
                     if s != PlayerState::Stop {
                         if let Err(e) = next_state.send(PlayerState::Stop).await {
                             tracing::error!("Could not stop scheduler: {e}");
@@ -54,6 +164,12 @@ pub async fn controller(
                     if let Err(e) = next_state.send(s).await {
                         tracing::error!("Could not start scheduler: {e}");
                     }
+                    if resume {
+                        tracing::info!("Test stopped, restarting scheduler");
+                        if let Err(e) = next_state.send(PlayerState::Schedule).await {
+                            tracing::error!("Could not restart scheduler: {e}");
+                        }
+                    }
                 }
             }
         }
@@ -77,10 +193,22 @@ pub async fn start_scheduler(
                             let cancel = cancel.child_token();
                             scheduler(state.clone(), cancel.clone(), &mut next_state).await;
                         },
+                        PlayerState::Playlist(name) => {
+                            let cancel = cancel.child_token();
+                            playlist(state.clone(), cancel.clone(), &mut next_state, name).await;
+                        },
+                        PlayerState::Sequence(name) => {
+                            let cancel = cancel.child_token();
+                            sequence(state.clone(), cancel.clone(), &mut next_state, name).await;
+                        },
                         PlayerState::Test(tests) => {
                             let cancel = cancel.child_token();
                             tester(state.clone(), cancel.clone(), &mut next_state, tests).await;
                         },
+                        PlayerState::Bridge => {
+                            let cancel = cancel.child_token();
+                            bridge(state.clone(), cancel.clone(), &mut next_state).await;
+                        },
                         _ => {}
                     }
                 }
@@ -96,16 +224,14 @@ async fn scheduler(
 ) {
     tracing::info!("Scheduler thread started");
 
-    {
-        let mut state = state.lock();
-        state.player_status = PlayerStatus::Scheduler;
-    }
+    let info = state.lock().player.clone();
+    info.send_modify(|p| p.status = PlayerStatus::Scheduler);
 
     let tracker = TaskTracker::new();
     let s = match start_senders(state.clone(), &tracker).await {
         Ok(s) => Some(s),
         Err(e) => {
-            tracing::error!("{e}");
+            report_error(&info, e.to_string());
             None
         }
     };
@@ -139,10 +265,7 @@ async fn scheduler(
         tracker.wait().await;
     }
 
-    {
-        let mut state = state.lock();
-        state.player_status = PlayerStatus::Stopped;
-    }
+    stopped(&info);
 
     tracing::info!("Scheduler thread stopped");
 }
@@ -156,9 +279,12 @@ async fn check_for_schedules(
     tracing::debug!("Checking for schedules");
 
     let mut next = None;
+    let info;
 
     {
         let mut state = state.lock();
+        info = state.player.clone();
+
         match db::get_current_schedule(&mut state.db_conn) {
             Ok(Some(s)) => {
                 tracing::debug!("Schedule found: {}", s.0.name);
@@ -168,16 +294,177 @@ async fn check_for_schedules(
                 tracing::debug!("No upcoming schedule");
             }
             Err(e) => {
-                tracing::error!("Error checking schedule: {e}");
+                report_error(&info, format!("Error checking schedule: {e}"));
+            }
+        }
+
+        match db::get_next_schedule(&mut state.db_conn) {
+            Ok(upcoming) => {
+                let upcoming = upcoming.map(|(schedule, playlist, start)| UpcomingSchedule {
+                    schedule: schedule.name,
+                    playlist: playlist.name,
+                    start: start.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                });
+                info.send_if_modified(|p| {
+                    let changed = p.next_schedule != upcoming;
+                    p.next_schedule = upcoming;
+                    changed
+                });
+            }
+            Err(e) => {
+                report_error(&info, format!("Error checking next schedule: {e}"));
             }
         }
     }
 
     if let Some(next) = next {
         if let Err(e) = play_schedule(state, next, cancel, s, player_state).await {
-            tracing::error!("Error playing schedule: {e}");
+            report_error(&info, format!("Error playing schedule: {e}"));
+        }
+    }
+}
+
+async fn playlist(
+    state: Arc<Mutex<State>>,
+    cancel: CancellationToken,
+    player_state: &mut Receiver<PlayerState>,
+    name: String,
+) {
+    let (playlist, info) = {
+        let mut state = state.lock();
+        (
+            db::get_playlist(&mut state.db_conn, name.clone()),
+            state.player.clone(),
+        )
+    };
+
+    let (playlist, sequences) = match playlist {
+        Ok(Some(p)) => p,
+        Ok(None) => {
+            report_error(&info, format!("Playlist not found: {name}"));
+            return;
+        }
+        Err(e) => {
+            report_error(&info, format!("Error loading playlist: {e}"));
+            return;
+        }
+    };
+
+    let sequences = sequences.into_iter().filter(|s| s.enabled).collect();
+
+    // A manually started playlist should always play at least once
+    let playlist = Playlist {
+        loop_count: playlist.loop_count.max(1),
+        ..playlist
+    };
+
+    play(
+        state,
+        cancel,
+        player_state,
+        PlayerStatus::Playlist,
+        playlist,
+        sequences,
+    )
+    .await;
+}
+
+async fn sequence(
+    state: Arc<Mutex<State>>,
+    cancel: CancellationToken,
+    player_state: &mut Receiver<PlayerState>,
+    name: String,
+) {
+    let (sequence, info) = {
+        let mut state = state.lock();
+        (
+            db::get_sequence(&mut state.db_conn, name.clone()),
+            state.player.clone(),
+        )
+    };
+
+    let sequence = match sequence {
+        Ok(Some((s, _))) => s,
+        Ok(None) => {
+            report_error(&info, format!("Sequence not found: {name}"));
+            return;
+        }
+        Err(e) => {
+            report_error(&info, format!("Error loading sequence: {e}"));
+            return;
+        }
+    };
+
+    // Play the sequence as a single item playlist
+    let playlist = Playlist {
+        id: 0,
+        name: name.clone(),
+        description: "".into(),
+        repeat: false,
+        loop_count: 1,
+    };
+
+    play(
+        state,
+        cancel,
+        player_state,
+        PlayerStatus::Sequence,
+        playlist,
+        vec![SequencePlus {
+            enabled: true,
+            play_once: true,
+            brightness: 100,
+            sequence,
+        }],
+    )
+    .await;
+}
+
+async fn play(
+    state: Arc<Mutex<State>>,
+    cancel: CancellationToken,
+    player_state: &mut Receiver<PlayerState>,
+    status: PlayerStatus,
+    playlist: Playlist,
+    sequences: Vec<SequencePlus>,
+) {
+    tracing::info!("Player thread started");
+
+    let info = state.lock().player.clone();
+    info.send_modify(|p| p.status = status);
+
+    let tracker = TaskTracker::new();
+    let s = match start_senders(state.clone(), &tracker).await {
+        Ok(s) => Some(s),
+        Err(e) => {
+            report_error(&info, e.to_string());
+            None
+        }
+    };
+    tracker.close();
+
+    if let Some(s) = s {
+        if let Err(e) = play_playlist(
+            state.clone(),
+            &playlist,
+            &sequences,
+            None,
+            cancel,
+            s.clone(),
+            player_state,
+        )
+        .await
+        {
+            report_error(&info, format!("Error playing playlist: {e}"));
         }
+
+        drop(s);
+        tracker.wait().await;
     }
+
+    stopped(&info);
+
+    tracing::info!("Player thread stopped");
 }
 
 async fn play_schedule(
@@ -189,15 +476,7 @@ async fn play_schedule(
 ) -> Result<()> {
     let (schedule, playlist, sequences) = next;
 
-    // Create an interval per framerate
-    let mut intervals = Vec::new();
-    for s in sequences.iter() {
-        intervals.push(tokio::time::interval(time::Duration::from_millis(
-            s.1.step_time as u64,
-        )))
-    }
-
-    // Set up an Interval to fire at the end of the schedule
+    // Work out when the schedule should end
     let end_time = NaiveTime::from_num_seconds_from_midnight_opt(schedule.end_time as u32, 0)
         .context("Cannot set end_time")?;
     let end_time = chrono::Local::now()
@@ -207,16 +486,69 @@ async fn play_schedule(
     let remaining = end_time - chrono::Local::now();
     let end = tokio::time::Instant::now()
         + tokio::time::Duration::from_secs(remaining.num_seconds() as u64);
-    let mut end = tokio::time::interval_at(end, time::Duration::from_millis(10));
 
     tracing::info!("Starting playist {}, ending at {}", playlist.name, end_time);
 
+    let info = state.lock().player.clone();
+    info.send_modify(|p| p.schedule = Some(schedule.name.clone()));
+
+    let res = play_playlist(
+        state,
+        &playlist,
+        &sequences,
+        Some(end),
+        cancel,
+        s,
+        player_state,
+    )
+    .await;
+
+    info.send_modify(|p| {
+        p.schedule = None;
+        idle(p);
+    });
+
+    res
+}
+
+async fn play_playlist(
+    state: Arc<Mutex<State>>,
+    playlist: &Playlist,
+    sequences: &[SequencePlus],
+    end: Option<tokio::time::Instant>,
+    cancel: CancellationToken,
+    s: Sender<Data>,
+    player_state: &mut Receiver<PlayerState>,
+) -> Result<()> {
+    let (info, master) = {
+        let state = state.lock();
+        (state.player.clone(), state.brightness.clone())
+    };
+    info.send_modify(|p| {
+        p.playlist = Some(playlist.name.clone());
+        p.playlist_count = sequences.len() as u32;
+    });
+
+    // Create an interval per framerate
+    let mut intervals = Vec::new();
+    for s in sequences.iter() {
+        intervals.push(tokio::time::interval(time::Duration::from_millis(
+            s.sequence.step_time as u64,
+        )))
+    }
+
     let mut loop_count = 0;
     let mut seq_idx = 0;
     let mut seq = None;
+    let mut dimmer = Dimmer::default();
 
     while !cancel.is_cancelled() && (playlist.repeat || loop_count < playlist.loop_count) {
-        let (play_once, sequence) = sequences.get(seq_idx).context("Couldn't get sequence")?;
+        let SequencePlus {
+            play_once,
+            brightness,
+            sequence,
+            ..
+        } = sequences.get(seq_idx).context("Couldn't get sequence")?;
         tracing::info!(
             "Playlist loop: {loop_count}, sequence: {}({seq_idx}){} - frames: {}@{}ms",
             sequence.name,
@@ -231,10 +563,21 @@ async fn play_schedule(
         if seq.is_none() {
             tracing::info!("Loading sequence: {}", sequence.name);
             let state = state.lock();
-            seq = storage::read_sequence_meta(&state.cfg, &sequence.name)
-                .context("Couldn't read sequence meta")?;
+            let path = StoragePath::new(&state.cfg, &sequence.name, StorageType::Sequences)?;
+            seq = storage::read_sequence_meta(&path).context("Couldn't read sequence meta")?;
         }
 
+        let step_time = sequence.step_time as u64;
+        info.send_modify(|p| {
+            p.sequence = Some(sequence.name.clone());
+            p.playlist_index = seq_idx as u32;
+            p.loop_count = loop_count as u32;
+            p.frame = 0;
+            p.frames = sequence.frames as u32;
+            p.elapsed_ms = 0;
+            p.remaining_ms = sequence.frames as u64 * step_time;
+        });
+
         let mut frame = 0;
         let int = intervals
             .get_mut(seq_idx)
@@ -243,7 +586,7 @@ async fn play_schedule(
         while frame < sequence.frames {
             tokio::select! {
                 _ = cancel.cancelled() => return Ok(()),
-                _ = end.tick() => return Ok(()),
+                _ = wait_until(end) => return Ok(()),
                 s = player_state.recv() => {
                     if let Some(s) = s {
                         if s == PlayerState::Stop {
@@ -255,17 +598,34 @@ async fn play_schedule(
                 _ = int.tick() => {
                     if let Some(ref mut seq) = seq {
                         match seq.get_frame(frame as u32) {
-                            Ok(Some(f)) => {
+                            Ok(Some(mut f)) => {
+                                // Read every frame so brightness changes are live
+                                dimmer.set(dimmer::combine(&[
+                                    *master.borrow() as i32,
+                                    sequence.brightness,
+                                    *brightness,
+                                ]));
+                                dimmer.apply(&mut f);
+
                                 s.send(Data{offset:0, data:f}).await.context("Couldn't send frame")?;
                             },
                             Ok(None) => break,
                             Err(e) => {
-                                tracing::error!("Error reading frame: {e}");
+                                report_error(&info, format!("Error reading frame: {e}"));
                                 break;
                             }
                         }
                     }
                     frame += 1;
+
+                    // Progress is picked up by the next poll rather than
+                    // notifying every subscriber on every frame
+                    info.send_if_modified(|p| {
+                        p.frame = frame as u32;
+                        p.elapsed_ms = frame as u64 * step_time;
+                        p.remaining_ms = (sequence.frames - frame) as u64 * step_time;
+                        false
+                    });
                 }
             }
         }
@@ -285,23 +645,68 @@ async fn play_schedule(
     Ok(())
 }
 
+fn idle(p: &mut PlayerInfo) {
+    p.playlist = None;
+    p.playlist_index = 0;
+    p.playlist_count = 0;
+    p.loop_count = 0;
+    p.sequence = None;
+    p.frame = 0;
+    p.frames = 0;
+    p.elapsed_ms = 0;
+    p.remaining_ms = 0;
+}
+
+fn report_error(info: &watch::Sender<PlayerInfo>, e: String) {
+    tracing::error!("{e}");
+    info.send_modify(|p| p.last_error = Some(e));
+}
+
+fn stopped(info: &watch::Sender<PlayerInfo>) {
+    info.send_modify(|p| {
+        *p = PlayerInfo {
+            last_error: p.last_error.take(),
+            recording: p.recording.take(),
+            ..Default::default()
+        }
+    });
+}
+
+async fn wait_until(end: Option<tokio::time::Instant>) {
+    match end {
+        Some(end) => tokio::time::sleep_until(end).await,
+        None => std::future::pending().await,
+    }
+}
+
 async fn start_senders(state: Arc<Mutex<State>>, tracker: &TaskTracker) -> Result<Sender<Data>> {
     // Load controllers
-    let mut controllers = Vec::new();
+    let mut outputs = Vec::new();
+    let models;
+    let bind;
 
     // Don't lock forever
     {
         let state = state.lock();
 
+        let settings = storage::read_output_settings(&state.cfg)
+            .map_err(|e| anyhow!("Could not read output settings: {e}"))?;
+
         match storage::read_outputs(&state.cfg) {
             Ok(channels) => {
                 for c in channels.channel_outputs.iter() {
                     for u in c.universes.iter() {
-                        controllers.push((
-                            u.address,
-                            u.start_channel as usize,
-                            u.channel_count as usize,
-                        ));
+                        outputs.push(Output {
+                            idx: outputs.len(),
+                            address: u.address,
+                            offset: u.start_channel as usize - 1,
+                            len: u.channel_count as usize,
+                            frame: vec![0; u.channel_count as usize],
+                            transform: Transform::new(settings.iter().find(|s| s.id == u.id)),
+                            conn: None,
+                            backoff: RETRY_MIN,
+                            retry_at: tokio::time::Instant::now(),
+                        });
                     }
                 }
             }
@@ -309,124 +714,300 @@ async fn start_senders(state: Arc<Mutex<State>>, tracker: &TaskTracker) -> Resul
                 return Err(anyhow!("Could not start senders: {e}"));
             }
         };
-    }
 
-    let mut senders = Vec::new();
-
-    let mut port = 4048;
-    for (ip, start, len) in controllers.iter() {
-        let (data_out, sender_rx) = mpsc::channel::<Data>(1);
-        tracker.spawn(sender(*ip, port, *len, sender_rx));
-        senders.push(SenderConfig {
-            offset: *start - 1,
-            len: *len,
-            chan: data_out,
+        models = storage::read_models(&state.cfg).unwrap_or_else(|e| {
+            tracing::warn!("Model overrides won't be applied, could not read models: {e}");
+            Vec::new()
         });
-        port += 1;
+
+        let output = state.cfg.output.as_ref();
+        bind = (
+            output.and_then(|o| o.bind).unwrap_or(Ipv4Addr::UNSPECIFIED),
+            output.and_then(|o| o.port).unwrap_or(0),
+        );
     }
 
-    senders.sort_by(|a, b| a.offset.cmp(&b.offset));
+    // Every controller is sent to from the same socket
+    let socket = std::net::UdpSocket::bind(bind)
+        .with_context(|| format!("Failed to bind output socket {}:{}", bind.0, bind.1))?;
 
-    // Spawn the demuxer
+    let (info, tap, overrides, master) = {
+        let state = state.lock();
+        (
+            state.player.clone(),
+            state.frames.clone(),
+            state.overrides.subscribe(),
+            state.brightness.subscribe(),
+        )
+    };
+    info.send_modify(|p| {
+        p.senders = outputs
+            .iter()
+            .map(|o| SenderStatus {
+                address: o.address,
+                healthy: true,
+                errors: 0,
+                last_error: None,
+            })
+            .collect();
+    });
+
+    // Spawn the sender
     let (s, r) = mpsc::channel::<Data>(1);
-    tracker.spawn(demuxer(r, senders));
+    let overlay = OverlaySource {
+        models,
+        overrides,
+        master,
+    };
+    tracker.spawn(sender(r, outputs, overlay, socket, info, tap));
 
     Ok(s)
 }
 
-async fn demuxer(mut data_in: Receiver<Data>, senders: Vec<SenderConfig>) {
-    tracing::info!("Started demuxer for {} controllers", senders.len());
+/// What the sender builds its overlay from
+struct OverlaySource {
+    models: Vec<Model>,
+    overrides: watch::Receiver<BTreeMap<String, Override>>,
+    /// Master brightness, for channels the overrides fill
+    master: watch::Receiver<u8>,
+}
+
+/// Splits frames between the outputs and sends them
+///
+/// Everything goes through one task and one socket so a frame is
+/// written to every controller in one go. What's sent, with the
+/// overrides applied, is copied to `tap` for previews and recordings.
+async fn sender<T: Connector>(
+    mut data_in: Receiver<Data>,
+    mut outputs: Vec<Output<T::Conn>>,
+    mut source: OverlaySource,
+    connector: T,
+    info: watch::Sender<PlayerInfo>,
+    tap: broadcast::Sender<Data>,
+) {
+    tracing::info!("Started sender for {} controllers", outputs.len());
+
+    let map = ChannelMap::new(
+        &outputs
+            .iter()
+            .map(|o| (o.offset, o.len))
+            .collect::<Vec<_>>(),
+    );
+    let mut overlay = Overlay::default();
+    source.overrides.mark_changed();
+
+    let mut updated = vec![false; outputs.len()];
+    // Every channel as it was last sent, for the tap
+    let mut sent = vec![0; outputs.iter().map(|o| o.offset + o.len).max().unwrap_or(0)];
 
     while let Some(data) = data_in.recv().await {
-        let mut d_start = data.offset;
-        let mut data = data.data.as_slice();
-
-        for cfg in senders.iter() {
-            let d_end = d_start + data.len() - 1;
-            let s_start = cfg.offset;
-            let s_end = s_start + cfg.len - 1;
-
-            if d_start >= s_start && d_start < s_end {
-                let offset = d_start - s_start;
-
-                if d_end <= s_end {
-                    cfg.chan
-                        .send(Data {
-                            offset,
-                            data: data.to_vec(),
-                        })
-                        .await
-                        .unwrap();
-                    break;
-                } else {
-                    let spl = data.split_at(s_end - d_start + 1);
-
-                    data = spl.1;
-
-                    cfg.chan
-                        .send(Data {
-                            offset,
-                            data: spl.0.to_vec(),
-                        })
-                        .await
-                        .unwrap();
-                    d_start = s_end + 1;
+        if source.overrides.has_changed().unwrap_or(false) {
+            overlay = Overlay::new(
+                &source.overrides.borrow_and_update(),
+                &source.models,
+                &map,
+                outputs.len(),
+            );
+        }
+        overlay.set_master(*source.master.borrow());
+
+        for t in map.map(data.offset, data.data.len()) {
+            let output = &mut outputs[t.output];
+            output.frame[t.offset..t.offset + t.range.len()].copy_from_slice(&data.data[t.range]);
+            updated[t.output] = true;
+        }
+
+        // Only copy the data if someone is watching
+        let watching = tap.receiver_count() > 0;
+        let mut tapped: Option<Range<usize>> = None;
+
+        for (i, (output, updated)) in outputs.iter_mut().zip(updated.iter_mut()).enumerate() {
+            if std::mem::take(updated) {
+                let mut frame = output.frame.clone();
+                overlay.apply(i, &mut frame);
+
+                if watching {
+                    let range = output.offset..output.offset + output.len;
+                    sent[range.clone()].copy_from_slice(&frame);
+                    tapped = Some(match tapped {
+                        Some(t) => t.start.min(range.start)..t.end.max(range.end),
+                        None => range,
+                    });
                 }
+
+                output.send(frame, &connector, &info);
             }
         }
+
+        if let Some(range) = tapped {
+            let _ = tap.send(Data {
+                offset: range.start,
+                data: sent[range].to_vec(),
+            });
+        }
     }
 
-    tracing::info!("Stopped demuxer for {} controllers", senders.len());
+    tracing::info!("Stopped sender for {} controllers", outputs.len());
 }
 
-async fn sender(ip: Ipv4Addr, port: u16, len: usize, mut r: Receiver<Data>) {
-    let mut conn = connection::DDPConnection::try_new(
-        format!("{ip}:4048"),
-        protocol::PixelConfig::default(),
-        protocol::ID::Default,
-        std::net::UdpSocket::bind(format!("0.0.0.0:{port}"))
-            .context("Failed to start UDP listener")
-            .unwrap(),
-    )
-    .context("Failed to create DDP connection")
-    .unwrap();
-
-    tracing::info!("Started sender for controller: {ip}");
-
-    while let Some(mut data) = r.recv().await {
-        let d_len = data.data.len();
-        let d_end = data.offset + d_len;
-
-        let to_send = match d_end.cmp(&len) {
-            Ordering::Equal => {
-                if data.offset == 0 {
-                    // Everything is ok
-                    Ok(data.data)
-                } else {
-                    // Pad start
-                    let mut d = vec![0u8; data.offset];
-                    d.append(&mut data.data);
-                    Ok(d)
-                }
+impl<C: Connection> Output<C> {
+    fn send<T: Connector<Conn = C>>(
+        &mut self,
+        frame: Vec<u8>,
+        connector: &T,
+        info: &watch::Sender<PlayerInfo>,
+    ) {
+        let ip = self.address;
+
+        if self.conn.is_none() {
+            if tokio::time::Instant::now() < self.retry_at {
+                return;
             }
-            Ordering::Less => {
-                // Pad end
-                let mut pad = vec![0u8; d_end - d_len];
-                data.data.append(&mut pad);
-                Ok(data.data)
+            match connector.connect(ip) {
+                Ok(c) => self.conn = Some(c),
+                Err(e) => {
+                    tracing::warn!("Failed to connect to controller {ip}: {e:#}");
+                    self.failed(info, format!("{e:#}"));
+                    return;
+                }
             }
-            Ordering::Greater => Err(anyhow!("Too much data for sender {}>{}", d_len, len)),
+        }
+
+        let Some(conn) = self.conn.as_mut() else {
+            return;
         };
 
-        match to_send {
-            Ok(data) => {
-                conn.write(&data).unwrap();
+        match conn.write(&self.transform.apply(frame)) {
+            Ok(_) => {
+                if self.backoff != RETRY_MIN {
+                    tracing::info!("Controller {ip} is sending again");
+                    self.backoff = RETRY_MIN;
+                }
+
+                info.send_if_modified(|p| match p.senders.get_mut(self.idx) {
+                    Some(s) if !s.healthy => {
+                        s.healthy = true;
+                        true
+                    }
+                    _ => false,
+                });
+            }
+            Err(e) => {
+                tracing::warn!("Failed to send to controller {ip}: {e:#}");
+                self.failed(info, format!("{e:#}"));
+
+                // Start again with a new connection once the backoff is over
+                self.conn = None;
+            }
+        }
+    }
+
+    fn failed(&mut self, info: &watch::Sender<PlayerInfo>, e: String) {
+        self.retry_at = tokio::time::Instant::now() + self.backoff;
+        self.backoff = (self.backoff * 2).min(RETRY_MAX);
+
+        info.send_modify(|p| {
+            if let Some(s) = p.senders.get_mut(self.idx) {
+                s.healthy = false;
+                s.errors += 1;
+                s.last_error = Some(e);
+            }
+        });
+    }
+}
+
+/// Forward DDP and E1.31 received from xLights to the controllers
+async fn bridge(
+    state: Arc<Mutex<State>>,
+    cancel: CancellationToken,
+    player_state: &mut Receiver<PlayerState>,
+) {
+    tracing::info!("Bridge thread started");
+
+    let (info, master, cfg, channels) = {
+        let state = state.lock();
+        // The bridge only needs to receive channels there are outputs for
+        let channels = storage::read_outputs(&state.cfg)
+            .map(|o| {
+                o.channel_outputs
+                    .iter()
+                    .flat_map(|c| c.universes.iter())
+                    .map(|u| {
+                        (u.start_channel as usize).saturating_sub(1) + u.channel_count as usize
+                    })
+                    .max()
+                    .unwrap_or(0)
+            })
+            .unwrap_or(0);
+        (
+            state.player.clone(),
+            state.brightness.clone(),
+            state.cfg.bridge.clone(),
+            channels,
+        )
+    };
+    info.send_modify(|p| p.status = PlayerStatus::Bridge);
+
+    let tracker = TaskTracker::new();
+    let s = match start_senders(state.clone(), &tracker).await {
+        Ok(s) => s,
+        Err(e) => {
+            report_error(&info, e.to_string());
+            stopped(&info);
+            return;
+        }
+    };
+
+    let (input, mut received) = mpsc::channel::<Data>(16);
+    let mut listener = {
+        let cancel = cancel.clone();
+        tracker.spawn(async move { bridge::listen(cfg.as_ref(), channels, input, cancel).await })
+    };
+    tracker.close();
+
+    let mut dimmer = Dimmer::default();
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            s = player_state.recv() => {
+                if let Some(s) = s {
+                    if s == PlayerState::Stop {
+                        cancel.cancel();
+                        break;
+                    }
+                }
+            }
+            res = &mut listener => {
+                match res {
+                    Ok(Err(e)) => report_error(&info, format!("{e:#}")),
+                    Err(e) => report_error(&info, format!("Bridge listener failed: {e}")),
+                    Ok(Ok(_)) => {}
+                }
+                break;
+            }
+            data = received.recv() => {
+                let Some(mut data) = data else {
+                    break;
+                };
+
+                dimmer.set(*master.borrow());
+                dimmer.apply(&mut data.data);
+                if let Err(e) = s.send(data).await {
+                    report_error(&info, format!("Couldn't send frame: {e}"));
+                    break;
+                }
             }
-            Err(e) => tracing::warn!("{e}"),
         }
     }
 
-    tracing::info!("Stopped sender for controller: {ip}");
+    // Stop the listener if it's still running
+    cancel.cancel();
+    drop(s);
+    tracker.wait().await;
+
+    stopped(&info);
+
+    tracing::info!("Bridge thread stopped");
 }
 
 async fn tester(
@@ -438,10 +1019,14 @@ async fn tester(
     tracing::info!("Testing thread started");
 
     let mut model_lookup = HashMap::new();
+    let info;
+    let master;
 
     // Don't lock forever
     {
-        let mut state = state.lock();
+        let state = state.lock();
+        info = state.player.clone();
+        master = state.brightness.clone();
 
         match storage::read_models(&state.cfg) {
             Ok(models) => {
@@ -450,35 +1035,47 @@ async fn tester(
                 }
             }
             Err(e) => {
-                tracing::error!("Could not start tester: {e}");
+                report_error(&info, format!("Could not start tester: {e}"));
                 return;
             }
         };
 
-        state.player_status = PlayerStatus::Testing;
+        info.send_modify(|p| p.status = PlayerStatus::Testing);
     }
 
-    // (start, len, sequence)
+    // (start, layout, channels per node, sequence)
     let mut test_setup = Vec::new();
     for (model, sequence) in tests.tests.iter() {
         if let Some(m) = model_lookup.get(model) {
-            if m.channel_count % 3 != 0 {
-                tracing::error!("Can't handle a non multiple of 3 channel count");
+            let channels = m.channel_count_per_node.max(1) as usize;
+            let start = m.start_channel.saturating_sub(1) as usize;
+            let nodes = m.channel_count as usize / channels;
+
+            if nodes == 0 {
+                report_error(&info, format!("Model '{model}' has no nodes to test"));
+                stopped(&info);
                 cancel.cancel();
                 return;
             }
 
-            let start = (m.start_channel - 1) / 3;
-            let len = m.channel_count / 3;
+            if let patterns::Sequence::Node(n) = sequence {
+                if n.number == 0 || n.number > nodes {
+                    report_error(&info, format!("Model '{model}' has no node {}", n.number));
+                    stopped(&info);
+                    cancel.cancel();
+                    return;
+                }
+            }
 
-            test_setup.push((start as usize, len as usize, sequence));
+            test_setup.push((start, patterns::Layout::new(m, nodes), channels, sequence));
         } else {
-            tracing::error!("Invalid model specified '{model}'");
+            report_error(&info, format!("Invalid model specified '{model}'"));
+            stopped(&info);
             cancel.cancel();
             return;
         }
     }
-    test_setup.sort_by(|a, b| a.0.cmp(&b.0));
+    test_setup.sort_by_key(|t| t.0);
 
     let mut intvl = tokio::time::interval(time::Duration::from_millis(tests.step_ms));
     intvl.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
@@ -487,14 +1084,16 @@ async fn tester(
     let s = match start_senders(state.clone(), &tracker).await {
         Ok(s) => s,
         Err(e) => {
-            tracing::error!("{e}");
+            report_error(&info, e.to_string());
+            stopped(&info);
             return;
         }
     };
     tracker.close();
 
+    let mut dimmer = Dimmer::default();
     let mut loop_count = 0;
-    loop {
+    'test: loop {
         tokio::select! {
             _ = cancel.cancelled() => break,
             s = player_state.recv() => {
@@ -506,17 +1105,20 @@ async fn tester(
                 }
             }
             _ = intvl.tick() => {
+                dimmer.set(*master.borrow());
+
                 let mut to_send = Vec::new();
                 let mut start_channel: Option<usize> = None;
                 let mut last_channel = 0;
 
-                for (start, len, seq) in test_setup.iter() {
+                for (start, layout, channels, seq) in test_setup.iter() {
                     if *start != last_channel && !to_send.is_empty() {
                         if let Some(start_channel) = start_channel {
-                            s.send(Data{offset: start_channel * 3, data: to_send})
-                                    .await
-                                    .context("Couldn't send frame")
-                                    .unwrap();
+                            dimmer.apply(&mut to_send);
+                            if let Err(e) = s.send(Data{offset: start_channel, data: to_send}).await {
+                                report_error(&info, format!("Couldn't send frame: {e}"));
+                                break 'test;
+                            }
                             to_send = Vec::new();
                         }
                         start_channel = None;
@@ -526,19 +1128,19 @@ async fn tester(
                         start_channel = Some(*start);
                     }
 
-                    let mut data = seq.as_vec(*len);
-                    if seq.moves() {
-                        data.rotate_right((loop_count % len) * 3);
-                    }
+                    let len = layout.len() * channels;
+                    let mut data = seq.as_vec(layout, loop_count, *channels);
+                    data.resize(len, 0);
                     to_send.append(&mut data);
                     last_channel = start + len;
                 }
 
                 if let Some(start_channel) = start_channel {
-                    s.send(Data{offset: start_channel * 3, data: to_send})
-                            .await
-                            .context("Couldn't send frame")
-                            .unwrap();
+                    dimmer.apply(&mut to_send);
+                    if let Err(e) = s.send(Data{offset: start_channel, data: to_send}).await {
+                        report_error(&info, format!("Couldn't send frame: {e}"));
+                        break;
+                    }
                 }
 
                 loop_count += 1
@@ -549,10 +1151,227 @@ async fn tester(
     drop(s);
     tracker.wait().await;
 
-    {
-        let mut state = state.lock();
-        state.player_status = PlayerStatus::Stopped;
-    }
+    stopped(&info);
 
     tracing::info!("Testing thread stopped");
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    /// Connects every time, counting the connections and writes, with
+    /// writes failing while `fail` is set
+    #[derive(Default)]
+    struct FakeConnector {
+        fail: Rc<Cell<bool>>,
+        connects: Cell<usize>,
+        writes: Rc<Cell<usize>>,
+    }
+
+    struct FakeConnection {
+        fail: Rc<Cell<bool>>,
+        writes: Rc<Cell<usize>>,
+    }
+
+    impl Connector for FakeConnector {
+        type Conn = FakeConnection;
+
+        fn connect(&self, _address: Ipv4Addr) -> Result<Self::Conn> {
+            self.connects.set(self.connects.get() + 1);
+            Ok(FakeConnection {
+                fail: self.fail.clone(),
+                writes: self.writes.clone(),
+            })
+        }
+    }
+
+    impl Connection for FakeConnection {
+        fn write(&mut self, _data: &[u8]) -> Result<()> {
+            if self.fail.get() {
+                return Err(anyhow!("Connection refused"));
+            }
+            self.writes.set(self.writes.get() + 1);
+            Ok(())
+        }
+    }
+
+    fn setup() -> (Output<FakeConnection>, watch::Sender<PlayerInfo>) {
+        let output = Output {
+            idx: 0,
+            address: Ipv4Addr::LOCALHOST,
+            offset: 0,
+            len: 3,
+            frame: vec![0; 3],
+            transform: Transform::new(None),
+            conn: None,
+            backoff: RETRY_MIN,
+            retry_at: tokio::time::Instant::now(),
+        };
+        let info = watch::channel(PlayerInfo {
+            senders: vec![SenderStatus {
+                address: Ipv4Addr::LOCALHOST,
+                healthy: true,
+                errors: 0,
+                last_error: None,
+            }],
+            ..Default::default()
+        })
+        .0;
+
+        (output, info)
+    }
+
+    /// Pretend the backoff is over
+    fn retry_now(output: &mut Output<FakeConnection>) {
+        output.retry_at = tokio::time::Instant::now();
+    }
+
+    #[test]
+    fn failure_marks_unhealthy() {
+        let (mut output, info) = setup();
+        let connector = FakeConnector::default();
+        connector.fail.set(true);
+
+        output.send(vec![1, 2, 3], &connector, &info);
+
+        let sender = info.borrow().senders[0].clone();
+        assert!(!sender.healthy);
+        assert_eq!(sender.errors, 1);
+        assert_eq!(sender.last_error.as_deref(), Some("Connection refused"));
+        assert!(output.conn.is_none());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let (mut output, info) = setup();
+        let connector = FakeConnector::default();
+        connector.fail.set(true);
+
+        let mut expected = RETRY_MIN;
+        for _ in 0..12 {
+            retry_now(&mut output);
+            output.send(vec![1, 2, 3], &connector, &info);
+
+            assert!(output.retry_at > tokio::time::Instant::now());
+            expected = (expected * 2).min(RETRY_MAX);
+            assert_eq!(output.backoff, expected);
+        }
+
+        assert_eq!(output.backoff, RETRY_MAX);
+        assert_eq!(info.borrow().senders[0].errors, 12);
+    }
+
+    #[test]
+    fn drops_frames_until_retry_at() {
+        let (mut output, info) = setup();
+        let connector = FakeConnector::default();
+        connector.fail.set(true);
+
+        output.send(vec![1, 2, 3], &connector, &info);
+        assert_eq!(connector.connects.get(), 1);
+
+        // The controller is back but the backoff isn't over
+        connector.fail.set(false);
+        output.send(vec![1, 2, 3], &connector, &info);
+        assert_eq!(connector.connects.get(), 1);
+        assert_eq!(connector.writes.get(), 0);
+
+        retry_now(&mut output);
+        output.send(vec![1, 2, 3], &connector, &info);
+        assert_eq!(connector.connects.get(), 2);
+        assert_eq!(connector.writes.get(), 1);
+    }
+
+    #[test]
+    fn success_resets_backoff() {
+        let (mut output, info) = setup();
+        let connector = FakeConnector::default();
+        connector.fail.set(true);
+
+        output.send(vec![1, 2, 3], &connector, &info);
+        retry_now(&mut output);
+        output.send(vec![1, 2, 3], &connector, &info);
+        assert!(output.backoff > RETRY_MIN);
+
+        connector.fail.set(false);
+        retry_now(&mut output);
+        output.send(vec![1, 2, 3], &connector, &info);
+
+        assert_eq!(output.backoff, RETRY_MIN);
+        let sender = info.borrow().senders[0].clone();
+        assert!(sender.healthy);
+        assert_eq!(sender.errors, 2);
+
+        // Connected now, so later frames go straight out
+        output.send(vec![1, 2, 3], &connector, &info);
+        assert_eq!(connector.connects.get(), 3);
+        assert_eq!(connector.writes.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn tap_sees_overrides() {
+        let (first, _) = setup();
+        let second = Output {
+            idx: 1,
+            offset: 3,
+            ..setup().0
+        };
+        let info = setup().1;
+        info.send_modify(|p| p.senders.push(p.senders[0].clone()));
+
+        let model = Model {
+            name: "Flood".into(),
+            x_lights: true,
+            channel_count: 3,
+            orientation: "horizontal".into(),
+            start_channel: 1,
+            string_count: 1,
+            channel_count_per_node: 3,
+            strands_per_string: 1,
+            start_corner: "BL".into(),
+            model_type: "Channel".into(),
+        };
+        let white = patterns::Color {
+            r: 255,
+            g: 255,
+            b: 255,
+        };
+        let (_overrides, overrides) =
+            watch::channel(BTreeMap::from([("Flood".into(), Override::Solid(white))]));
+        let (_master, master) = watch::channel(50);
+        let source = OverlaySource {
+            models: vec![model],
+            overrides,
+            master,
+        };
+
+        let (tap, mut tapped) = broadcast::channel(4);
+        let (data, data_in) = mpsc::channel(1);
+        data.send(Data {
+            offset: 0,
+            data: vec![10, 20, 30, 40, 50, 60],
+        })
+        .await
+        .unwrap();
+        drop(data);
+
+        sender(
+            data_in,
+            vec![first, second],
+            source,
+            FakeConnector::default(),
+            info,
+            tap,
+        )
+        .await;
+
+        // The flood is filled at the master brightness, the rest is
+        // sent as it came in
+        let sent = tapped.recv().await.unwrap();
+        assert_eq!(sent.offset, 0);
+        assert_eq!(sent.data, vec![55, 55, 55, 40, 50, 60]);
+    }
+}
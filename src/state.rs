@@ -1,14 +1,22 @@
+use std::collections::BTreeMap;
+
 use diesel::SqliteConnection;
-use tokio::sync::mpsc::Sender;
+use tokio::sync::{broadcast, mpsc::Sender, watch};
 
 use crate::{
     config::Config,
-    models::{PlayerState, PlayerStatus},
+    models::{Override, PlayerInfo, PlayerState},
+    player::Data,
 };
 
 pub struct State {
     pub cfg: Config,
     pub db_conn: SqliteConnection,
     pub player_ctrl: Sender<PlayerState>,
-    pub player_status: PlayerStatus,
+    pub player: watch::Sender<PlayerInfo>,
+    pub frames: broadcast::Sender<Data>,
+    /// Master brightness as a percentage
+    pub brightness: watch::Sender<u8>,
+    /// Manual control of models by name
+    pub overrides: watch::Sender<BTreeMap<String, Override>>,
 }
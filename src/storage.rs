@@ -2,18 +2,23 @@ use std::{
     fmt::Display,
     fs::{File, OpenOptions},
     io::{Read, Write},
-    path::Path,
+    path::{Component, Path, PathBuf},
 };
 
 use anyhow::{anyhow, bail, Context, Result};
 use rustix::path::Arg;
+use sha2::{Digest, Sha256};
 
 use crate::{
     config::Config,
     fseq,
-    models::{Channels, Model},
+    models::{Channels, Model, OutputSettings},
 };
 
+/// File holding the settings of each universe
+pub const OUTPUT_SETTINGS: &str = "output_settings.json";
+
+#[derive(Debug, Clone, Copy)]
 pub enum StorageType {
     Sequences,
     Media,
@@ -32,6 +37,63 @@ impl Display for StorageType {
     }
 }
 
+/// A file inside one of the storage directories
+///
+/// Filenames come from clients so they are checked to make sure they
+/// can't escape the directory, either with `..`, an absolute path, or
+/// by following a symlink which points elsewhere.
+#[derive(Debug, Clone)]
+pub struct StoragePath(PathBuf);
+
+impl StoragePath {
+    pub fn new(cfg: &Config, filename: &str, st: StorageType) -> Result<Self> {
+        Self::within(&Path::new(&cfg.storage).join(st.to_string()), filename)
+    }
+
+    /// Check a filename against any directory, such as the log directory
+    pub fn within(dir: &Path, filename: &str) -> Result<Self> {
+        let mut components = Path::new(filename).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => {}
+            _ => bail!("Invalid filename '{filename}'"),
+        }
+
+        let path = dir.join(filename);
+
+        // Existing files may be symlinks so make sure they resolve to
+        // somewhere inside the directory
+        if path.symlink_metadata().is_ok() {
+            let dir = dir
+                .canonicalize()
+                .with_context(|| format!("Could not resolve {dir:?}"))?;
+            match path.canonicalize() {
+                Ok(p) if p.starts_with(&dir) => {}
+                _ => bail!("Invalid filename '{filename}'"),
+            }
+        }
+
+        Ok(Self(path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// The filename without the directory
+    pub fn filename(&self) -> String {
+        self.0
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+impl AsRef<Path> for StoragePath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
 pub fn init(cfg: &Config) -> Result<()> {
     let path = Path::new(&cfg.storage);
 
@@ -84,10 +146,8 @@ pub fn get_dir(filename: &String) -> Option<StorageType> {
     }
 }
 
-pub fn read_file(cfg: &Config, filename: &String, st: StorageType) -> Result<Option<Vec<u8>>> {
-    let path = Path::new(&cfg.storage).join(st.to_string()).join(filename);
-
-    if path.exists() {
+pub fn read_file(path: &StoragePath) -> Result<Option<Vec<u8>>> {
+    if path.path().exists() {
         match File::open(path) {
             Ok(mut f) => match f.metadata() {
                 Ok(meta) => {
@@ -106,9 +166,7 @@ pub fn read_file(cfg: &Config, filename: &String, st: StorageType) -> Result<Opt
     }
 }
 
-pub fn upload_file(cfg: &Config, filename: &String, st: StorageType, data: Vec<u8>) -> Result<()> {
-    let path = Path::new(&cfg.storage).join(st.to_string()).join(filename);
-
+pub fn upload_file(path: &StoragePath, data: Vec<u8>) -> Result<()> {
     let mut f = OpenOptions::new()
         .write(true)
         .truncate(true)
@@ -119,23 +177,32 @@ pub fn upload_file(cfg: &Config, filename: &String, st: StorageType, data: Vec<u
     Ok(())
 }
 
-pub fn read_sequence_meta(cfg: &Config, filename: &String) -> Result<Option<fseq::parser::FSeq>> {
-    let path = Path::new(&cfg.storage)
-        .join(StorageType::Sequences.to_string())
-        .join(filename);
-
-    if !path.exists() {
+pub fn read_sequence_meta(path: &StoragePath) -> Result<Option<fseq::parser::FSeq>> {
+    if !path.path().exists() {
         return Ok(None);
     }
 
+    let path = path.path();
     let path = path.as_str()?;
     Ok(Some(*fseq::parser::parse(path)?))
 }
 
-pub fn del_file(cfg: &Config, filename: &String, st: StorageType) -> Result<()> {
-    let path = Path::new(&cfg.storage).join(st.to_string()).join(filename);
+/// SHA-256 of a file as hex
+pub fn checksum(path: &StoragePath) -> Result<String> {
+    let mut file = File::open(path).context("Could not open file")?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).context("Could not read file")?;
+
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Lowercase hex of some bytes, such as a checksum
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
 
-    if !path.exists() {
+pub fn del_file(path: &StoragePath) -> Result<()> {
+    if !path.path().exists() {
         return Ok(());
     }
 
@@ -159,6 +226,29 @@ pub fn read_outputs(cfg: &Config) -> Result<Channels> {
     serde_json::from_str::<Channels>(&contents).map_err(|e| anyhow!(e))
 }
 
+/// Colour order and gamma of each universe, which are kept apart from
+/// outputs.json so uploads from xLights don't replace them
+pub fn read_output_settings(cfg: &Config) -> Result<Vec<OutputSettings>> {
+    let filename = Path::new(&cfg.storage)
+        .join(StorageType::Other.to_string())
+        .join(OUTPUT_SETTINGS);
+
+    if !filename.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .open(filename)
+        .context("Could not open file")?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .context("Could not read file")?;
+
+    serde_json::from_str::<Vec<OutputSettings>>(&contents).map_err(|e| anyhow!(e))
+}
+
 pub fn read_models(cfg: &Config) -> Result<Vec<Model>> {
     let filename = Path::new(&cfg.storage)
         .join(StorageType::Other.to_string())
@@ -182,3 +272,77 @@ pub fn output_exists(cfg: &Config) -> bool {
         .join("outputs.json")
         .exists()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+
+    #[test]
+    fn accepts_plain_filenames() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = StoragePath::within(dir.path(), "show.fseq").unwrap();
+        assert_eq!(path.path(), dir.path().join("show.fseq"));
+        assert_eq!(path.filename(), "show.fseq");
+    }
+
+    #[test]
+    fn rejects_parent_directories() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(StoragePath::within(dir.path(), "..").is_err());
+        assert!(StoragePath::within(dir.path(), "../show.fseq").is_err());
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(StoragePath::within(dir.path(), "/etc/passwd").is_err());
+        assert!(StoragePath::within(dir.path(), "/").is_err());
+    }
+
+    #[test]
+    fn rejects_multiple_components() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(StoragePath::within(dir.path(), "sub/show.fseq").is_err());
+        assert!(StoragePath::within(dir.path(), "./show.fseq").is_err());
+        assert!(StoragePath::within(dir.path(), "").is_err());
+    }
+
+    #[test]
+    fn rejects_symlinks_outside() {
+        let outside = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret"), "secret").unwrap();
+        symlink(outside.path().join("secret"), dir.path().join("show.fseq")).unwrap();
+
+        assert!(StoragePath::within(dir.path(), "show.fseq").is_err());
+    }
+
+    #[test]
+    fn accepts_symlinks_inside() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.fseq"), "").unwrap();
+        symlink(dir.path().join("a.fseq"), dir.path().join("b.fseq")).unwrap();
+
+        assert!(StoragePath::within(dir.path(), "b.fseq").is_ok());
+    }
+
+    #[test]
+    fn uses_the_storage_type_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg: Config = toml::from_str(&format!(
+            "database_url = \":memory:\"\nstorage = {:?}",
+            dir.path()
+        ))
+        .unwrap();
+
+        let path = StoragePath::new(&cfg, "tree.obj", StorageType::Meshes).unwrap();
+        assert_eq!(path.path(), dir.path().join("meshes").join("tree.obj"));
+        assert!(StoragePath::new(&cfg, "../other/models.json", StorageType::Meshes).is_err());
+    }
+}
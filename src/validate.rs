@@ -0,0 +1,261 @@
+use std::cmp::Ordering;
+
+use crate::{
+    fseq::parser::FSeq,
+    models::{Channels, Model},
+};
+
+/// Most model names listed in a warning
+const MAX_LISTED_MODELS: usize = 5;
+
+/// Compare a sequence with the outputs and models uploaded from xLights
+///
+/// Problems are returned as warnings rather than errors as xLights will
+/// happily upload a sequence which doesn't match the display, and it
+/// may still be worth playing.
+pub fn sequence_warnings(
+    seq: &FSeq,
+    outputs: Option<&Channels>,
+    models: Option<&[Model]>,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    // Zero based channel ranges, end exclusive, held by the sequence
+    let ranges = match seq.sparse_ranges.is_empty() {
+        true => vec![(0, seq.channel_count)],
+        false => seq
+            .sparse_ranges
+            .iter()
+            .map(|r| (r.start_channel, r.start_channel + r.end_channel_offset))
+            .collect(),
+    };
+
+    if let Some(outputs) = outputs {
+        let outputs_end = outputs
+            .channel_outputs
+            .iter()
+            .flat_map(|o| o.universes.iter())
+            .map(|u| u.start_channel.saturating_sub(1) + u.channel_count)
+            .max()
+            .unwrap_or(0);
+
+        if seq.sparse_ranges.is_empty() {
+            match seq.channel_count.cmp(&outputs_end) {
+                Ordering::Less => warnings.push(format!(
+                    "Sequence is {} channels short of outputs",
+                    outputs_end - seq.channel_count
+                )),
+                Ordering::Greater => warnings.push(format!(
+                    "Sequence has {} more channels than outputs",
+                    seq.channel_count - outputs_end
+                )),
+                Ordering::Equal => {}
+            }
+        } else {
+            for (start, end) in ranges.iter().filter(|(_, end)| *end > outputs_end) {
+                warnings.push(format!(
+                    "Sparse range {}-{} is {} channels past the end of outputs",
+                    start + 1,
+                    end,
+                    end - outputs_end.max(*start)
+                ));
+            }
+        }
+    }
+
+    if let Some(models) = models {
+        let uncovered = models
+            .iter()
+            .filter(|m| {
+                let start = m.start_channel.saturating_sub(1);
+                let end = start + m.channel_count;
+                !ranges.iter().any(|(s, e)| *s <= start && end <= *e)
+            })
+            .map(|m| m.name.as_str())
+            .collect::<Vec<_>>();
+
+        if !uncovered.is_empty() {
+            let mut names = uncovered
+                .iter()
+                .take(MAX_LISTED_MODELS)
+                .copied()
+                .collect::<Vec<_>>()
+                .join(", ");
+            if uncovered.len() > MAX_LISTED_MODELS {
+                names += &format!(" and {} more", uncovered.len() - MAX_LISTED_MODELS);
+            }
+
+            warnings.push(format!(
+                "{} model(s) aren't fully covered by the sequence: {names}",
+                uncovered.len()
+            ));
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fseq::{parser, writer::Writer};
+
+    use super::*;
+
+    /// A single frame sequence, read back through the parser
+    fn sequence(channel_count: u32, sparse: &[(u32, u32)]) -> Box<FSeq> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seq.fseq");
+
+        let sparse = sparse
+            .iter()
+            .map(|(start, len)| parser::SparseRange {
+                start_channel: *start,
+                end_channel_offset: *len,
+            })
+            .collect::<Vec<_>>();
+        let file = std::fs::File::create(&path).unwrap();
+        let mut w = Writer::new(file, channel_count, 1, 50, &sparse, &[]).unwrap();
+        w.write_frame(&[]).unwrap();
+        w.finish().unwrap();
+
+        parser::parse(path.to_str().unwrap()).unwrap()
+    }
+
+    /// Outputs made of universes at `(start_channel, channel_count)`,
+    /// start channels being one based
+    fn outputs(universes: &[(u32, u32)]) -> Channels {
+        let universes = universes
+            .iter()
+            .enumerate()
+            .map(|(i, (start, count))| {
+                serde_json::json!({
+                    "description": "",
+                    "active": 1,
+                    "address": "10.0.0.1",
+                    "startChannel": start,
+                    "channelCount": count,
+                    "id": i + 1,
+                    "deDuplicate": 0,
+                    "priority": 0,
+                    "monitor": 1,
+                    "type": 4,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::from_value(serde_json::json!({
+            "channelOutputs": [{
+                "type": "universes",
+                "startChannel": 1,
+                "enabled": 1,
+                "timeout": 1000,
+                "channelCount": -1,
+                "universes": universes,
+            }]
+        }))
+        .unwrap()
+    }
+
+    /// A model at a one based start channel
+    fn model(name: &str, start_channel: u32, channel_count: u32) -> Model {
+        Model {
+            name: name.into(),
+            x_lights: true,
+            channel_count,
+            orientation: "horizontal".into(),
+            start_channel,
+            string_count: 1,
+            channel_count_per_node: 3,
+            strands_per_string: 1,
+            start_corner: "BL".into(),
+            model_type: "Channel".into(),
+        }
+    }
+
+    #[test]
+    fn nothing_configured() {
+        let seq = sequence(300, &[]);
+        assert!(sequence_warnings(&seq, None, None).is_empty());
+
+        let sparse = sequence(300, &[(1000, 300)]);
+        assert!(sequence_warnings(&sparse, None, None).is_empty());
+    }
+
+    #[test]
+    fn matching_outputs_and_models() {
+        let seq = sequence(1020, &[]);
+        let outputs = outputs(&[(1, 510), (511, 510)]);
+        let models = [model("Arch", 1, 150), model("Tree", 511, 510)];
+
+        assert!(sequence_warnings(&seq, Some(&outputs), Some(&models)).is_empty());
+    }
+
+    #[test]
+    fn channel_count_differs_from_outputs() {
+        let outputs = outputs(&[(1, 510), (511, 510)]);
+
+        let short = sequence(1000, &[]);
+        assert_eq!(
+            sequence_warnings(&short, Some(&outputs), None),
+            vec!["Sequence is 20 channels short of outputs"]
+        );
+
+        let long = sequence(1100, &[]);
+        assert_eq!(
+            sequence_warnings(&long, Some(&outputs), None),
+            vec!["Sequence has 80 more channels than outputs"]
+        );
+    }
+
+    #[test]
+    fn sparse_ranges_past_outputs() {
+        let outputs = outputs(&[(1, 510)]);
+
+        let inside = sequence(300, &[(0, 150), (200, 150)]);
+        assert!(sequence_warnings(&inside, Some(&outputs), None).is_empty());
+
+        let past = sequence(300, &[(0, 150), (450, 100), (600, 50)]);
+        assert_eq!(
+            sequence_warnings(&past, Some(&outputs), None),
+            vec![
+                "Sparse range 451-550 is 40 channels past the end of outputs",
+                "Sparse range 601-650 is 50 channels past the end of outputs",
+            ]
+        );
+    }
+
+    #[test]
+    fn models_not_covered() {
+        let seq = sequence(300, &[]);
+        let models = [
+            model("Arch", 1, 150),
+            model("Tree", 151, 300),
+            model("Star", 400, 3),
+        ];
+        assert_eq!(
+            sequence_warnings(&seq, None, Some(&models)),
+            vec!["2 model(s) aren't fully covered by the sequence: Tree, Star"]
+        );
+
+        // Covered by a sparse range, but not split across two
+        let sparse = sequence(300, &[(0, 150), (150, 150)]);
+        let models = [model("Arch", 1, 150), model("Split", 100, 100)];
+        assert_eq!(
+            sequence_warnings(&sparse, None, Some(&models)),
+            vec!["1 model(s) aren't fully covered by the sequence: Split"]
+        );
+    }
+
+    #[test]
+    fn lists_a_few_models() {
+        let seq = sequence(3, &[]);
+        let models = (1..=7)
+            .map(|i| model(&format!("M{i}"), i * 10, 3))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            sequence_warnings(&seq, None, Some(&models)),
+            vec!["7 model(s) aren't fully covered by the sequence: M1, M2, M3, M4, M5 and 2 more"]
+        );
+    }
+}
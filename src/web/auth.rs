@@ -0,0 +1,478 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{self, ConnectInfo, MatchedPath, Request},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use parking_lot::Mutex;
+
+use crate::{
+    auth::{self, Principal},
+    db::{
+        self,
+        models::{ApiToken, NewApiToken, NewSession, NewUser, Role},
+    },
+    models::*,
+    state::State,
+    web::error::APIError,
+};
+
+/// Check the request is allowed to use the route it matched
+///
+/// When authentication is disabled every request is treated as
+/// coming from an admin.
+pub async fn authorize(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let authorization = header_value(&req, header::AUTHORIZATION);
+    let cookie = header_value(&req, header::COOKIE);
+
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_default();
+    let addr = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|c| c.0.ip());
+
+    let (principal, fpp_allowed) = {
+        let mut state = state.lock();
+
+        if !auth::enabled(&state.cfg) {
+            (
+                Some(Principal {
+                    name: "".into(),
+                    role: Role::Admin,
+                }),
+                false,
+            )
+        } else {
+            let fpp_allowed = auth::is_fpp_compatible(req.method().as_str(), &path)
+                && addr.is_some_and(|ip| auth::fpp_allowed(state.cfg.auth.as_ref(), ip));
+
+            match auth::authenticate(
+                &mut state.db_conn,
+                authorization.as_deref(),
+                cookie.as_deref(),
+            ) {
+                Ok(p) => (p, fpp_allowed),
+                Err(e) => return APIError::UnexpectedError(e).into_response(),
+            }
+        }
+    };
+
+    if let Some(required) = auth::required_role(req.method().as_str(), &path) {
+        match &principal {
+            Some(p) if p.role >= required => {}
+            _ if fpp_allowed => {}
+            Some(p) => {
+                return APIError::Forbidden(format!("'{}' can't do that", p.name)).into_response()
+            }
+            None => return APIError::Unauthorized("Not logged in".into()).into_response(),
+        }
+    }
+
+    if let Some(p) = principal {
+        req.extensions_mut().insert(p);
+    }
+
+    next.run(req).await
+}
+
+fn header_value(req: &Request, name: header::HeaderName) -> Option<String> {
+    req.headers().get(name)?.to_str().ok().map(str::to_string)
+}
+
+/// Log in
+///
+/// Log in with a username and password, starting a session
+#[utoipa::path(
+    post,
+    path = "/api/auth/login",
+    request_body(content = Credentials),
+    responses(
+        (status = 200, description = "Logged in, the session cookie is set", body = CurrentUser),
+        (status = 401, description = "Wrong username or password", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Auth"
+)]
+pub async fn login(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    Json(credentials): Json<Credentials>,
+) -> Response {
+    let mut state = state.lock();
+
+    let user = match db::get_user(&mut state.db_conn, &credentials.username) {
+        Ok(Some(u)) if auth::verify_password(&credentials.password, &u.password_hash) => u,
+        Ok(_) => {
+            return APIError::Unauthorized("Wrong username or password".into()).into_response()
+        }
+        Err(e) => return APIError::UnexpectedError(e).into_response(),
+    };
+
+    let token = auth::generate_token();
+    let max_age = auth::session_secs(&state.cfg);
+
+    if let Err(e) = db::new_session(
+        &mut state.db_conn,
+        NewSession {
+            token_hash: auth::hash_token(&token),
+            user_id: user.id,
+            expires: chrono::Utc::now().timestamp() + max_age,
+        },
+    ) {
+        return APIError::UnexpectedError(e).into_response();
+    }
+
+    (
+        [(
+            header::SET_COOKIE,
+            format!(
+                "{}={token}; Path=/; HttpOnly; SameSite=Strict; Max-Age={max_age}",
+                auth::SESSION_COOKIE
+            ),
+        )],
+        Json(CurrentUser {
+            username: user.username,
+            role: user.role,
+            auth_enabled: auth::enabled(&state.cfg),
+        }),
+    )
+        .into_response()
+}
+
+/// Log out
+///
+/// End the current session
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout",
+    responses(
+        (status = 200, description = "Logged out", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Auth"
+)]
+pub async fn logout(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    headers: HeaderMap,
+) -> Response {
+    let token = headers
+        .get(header::COOKIE)
+        .and_then(|c| c.to_str().ok())
+        .and_then(auth::session_token);
+
+    if let Some(token) = token {
+        let mut state = state.lock();
+        if let Err(e) = db::del_session(&mut state.db_conn, &auth::hash_token(token)) {
+            return APIError::UnexpectedError(e).into_response();
+        }
+    }
+
+    (
+        [(
+            header::SET_COOKIE,
+            format!(
+                "{}=; Path=/; HttpOnly; SameSite=Strict; Max-Age=0",
+                auth::SESSION_COOKIE
+            ),
+        )],
+        APIError::Ok,
+    )
+        .into_response()
+}
+
+/// Get the current user
+///
+/// Get who is logged in and what they're allowed to do
+#[utoipa::path(
+    get,
+    path = "/api/auth/me",
+    responses(
+        (status = 200, description = "The current user", body = CurrentUser),
+        (status = 401, description = "Not logged in", body = Status)
+    ),
+    tag = "Auth"
+)]
+pub async fn current_user(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    Extension(principal): Extension<Principal>,
+) -> Response {
+    let auth_enabled = auth::enabled(&state.lock().cfg);
+
+    Json(CurrentUser {
+        username: principal.name,
+        role: principal.role,
+        auth_enabled,
+    })
+    .into_response()
+}
+
+/// List users
+///
+/// List all users and their roles
+#[utoipa::path(
+    get,
+    path = "/api/users",
+    responses(
+        (status = 200, description = "List of users", body = [UserDetails]),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Auth"
+)]
+pub async fn list_users(extract::State(state): extract::State<Arc<Mutex<State>>>) -> Response {
+    let mut state = state.lock();
+
+    match db::get_users(&mut state.db_conn) {
+        Ok(users) => Json(
+            users
+                .into_iter()
+                .map(|u| UserDetails {
+                    username: u.username,
+                    role: u.role,
+                })
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// Create a user
+///
+/// Add a user who can log in to the web interface
+#[utoipa::path(
+    post,
+    path = "/api/user",
+    request_body(content = UserRequest),
+    responses(
+        (status = 200, description = "User created", body = Status),
+        (status = 400, description = "The user already exists", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Auth"
+)]
+pub async fn new_user(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    Json(user): Json<UserRequest>,
+) -> Response {
+    if user.username.is_empty() || user.password.is_empty() {
+        return APIError::BadRequest("Username and password are required".into()).into_response();
+    }
+
+    let mut state = state.lock();
+
+    match db::get_user(&mut state.db_conn, &user.username) {
+        Ok(Some(_)) => {
+            return APIError::BadRequest(format!("User '{}' already exists", user.username))
+                .into_response()
+        }
+        Ok(None) => {}
+        Err(e) => return APIError::UnexpectedError(e).into_response(),
+    }
+
+    let password_hash = match auth::hash_password(&user.password) {
+        Ok(h) => h,
+        Err(e) => return APIError::UnexpectedError(e).into_response(),
+    };
+
+    match db::new_user(
+        &mut state.db_conn,
+        NewUser {
+            username: user.username,
+            password_hash,
+            role: user.role,
+        },
+    ) {
+        Ok(_) => APIError::Ok.into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// Update a user
+///
+/// Change the password or role of a user
+#[utoipa::path(
+    put,
+    path = "/api/user/{username}",
+    params(
+        ("username" = String, Path, description = "The user to update")
+    ),
+    request_body(content = UserUpdate),
+    responses(
+        (status = 200, description = "User updated", body = Status),
+        (status = 404, description = "User not found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Auth"
+)]
+pub async fn update_user(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path(username): extract::Path<String>,
+    Json(update): Json<UserUpdate>,
+) -> Response {
+    let password_hash = match update.password.as_deref() {
+        Some("") => return APIError::BadRequest("Password is required".into()).into_response(),
+        Some(p) => match auth::hash_password(p) {
+            Ok(h) => Some(h),
+            Err(e) => return APIError::UnexpectedError(e).into_response(),
+        },
+        None => None,
+    };
+
+    let mut state = state.lock();
+
+    match db::update_user(&mut state.db_conn, &username, password_hash, update.role) {
+        Ok(Some(_)) => APIError::Ok.into_response(),
+        Ok(None) => APIError::NotFound("User".into()).into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// Delete a user
+///
+/// Remove a user and end their sessions. You can't delete yourself.
+#[utoipa::path(
+    delete,
+    path = "/api/user/{username}",
+    params(
+        ("username" = String, Path, description = "The user to delete")
+    ),
+    responses(
+        (status = 200, description = "User deleted", body = Status),
+        (status = 400, description = "Tried to delete yourself", body = Status),
+        (status = 404, description = "User not found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Auth"
+)]
+pub async fn del_user(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    Extension(principal): Extension<Principal>,
+    extract::Path(username): extract::Path<String>,
+) -> Response {
+    if principal.name == username {
+        return APIError::BadRequest("You can't delete yourself".into()).into_response();
+    }
+
+    let mut state = state.lock();
+
+    match db::del_user(&mut state.db_conn, &username) {
+        Ok(Some(_)) => APIError::Ok.into_response(),
+        Ok(None) => APIError::NotFound("User".into()).into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// List API tokens
+///
+/// List the API tokens, without the tokens themselves
+#[utoipa::path(
+    get,
+    path = "/api/tokens",
+    responses(
+        (status = 200, description = "List of API tokens", body = [ApiToken]),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Auth"
+)]
+pub async fn list_tokens(extract::State(state): extract::State<Arc<Mutex<State>>>) -> Response {
+    let mut state = state.lock();
+
+    match db::get_tokens(&mut state.db_conn) {
+        Ok(tokens) => Json(tokens).into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// Create an API token
+///
+/// Create a token for clients such as xLights. It can be sent as a
+/// bearer token or as the password for basic auth. The token is only
+/// returned once.
+#[utoipa::path(
+    post,
+    path = "/api/token",
+    request_body(content = TokenRequest),
+    responses(
+        (status = 200, description = "Token created", body = NewToken),
+        (status = 400, description = "The token already exists", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Auth"
+)]
+pub async fn new_token(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    Json(request): Json<TokenRequest>,
+) -> Response {
+    if request.name.is_empty() {
+        return APIError::BadRequest("Name is required".into()).into_response();
+    }
+
+    let mut state = state.lock();
+
+    match db::get_tokens(&mut state.db_conn) {
+        Ok(tokens) if tokens.iter().any(|t| t.name == request.name) => {
+            return APIError::BadRequest(format!("Token '{}' already exists", request.name))
+                .into_response()
+        }
+        Ok(_) => {}
+        Err(e) => return APIError::UnexpectedError(e).into_response(),
+    }
+
+    let token = auth::generate_token();
+
+    match db::new_token(
+        &mut state.db_conn,
+        NewApiToken {
+            name: request.name.clone(),
+            token_hash: auth::hash_token(&token),
+            role: request.role,
+        },
+    ) {
+        Ok(_) => Json(NewToken {
+            name: request.name,
+            role: request.role,
+            token,
+        })
+        .into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// Delete an API token
+///
+/// Revoke an API token
+#[utoipa::path(
+    delete,
+    path = "/api/token/{name}",
+    params(
+        ("name" = String, Path, description = "The token to delete")
+    ),
+    responses(
+        (status = 200, description = "Token deleted", body = Status),
+        (status = 404, description = "Token not found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Auth"
+)]
+pub async fn del_token(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path(name): extract::Path<String>,
+) -> Response {
+    let mut state = state.lock();
+
+    match db::del_token(&mut state.db_conn, &name) {
+        Ok(Some(_)) => APIError::Ok.into_response(),
+        Ok(None) => APIError::NotFound("Token".into()).into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use axum::{
+    extract,
+    response::{IntoResponse, Response},
+    Json,
+};
+use parking_lot::Mutex;
+
+use crate::{
+    config::Config,
+    display::{self, VirtualDisplay},
+    models::Status,
+    state::State,
+    storage::{self, StoragePath, StorageType},
+    web::error::APIError,
+};
+
+/// Get the virtual display
+///
+/// Get the parsed VirtualDisplayMap, with the position and channels
+/// of every pixel and the bounding box of every model
+#[utoipa::path(
+    get,
+    path = "/api/display",
+    responses(
+        (status = 200, description = "The virtual display", body = VirtualDisplay),
+        (status = 404, description = "VirtualDisplayMap not found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Display"
+)]
+pub async fn get_virtual_display(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+) -> Response {
+    let state = state.lock();
+
+    match read_virtual_display(&state.cfg) {
+        Ok(d) => Json(d).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Read and parse the VirtualDisplayMap
+pub fn read_virtual_display(cfg: &Config) -> Result<VirtualDisplay, APIError> {
+    let data = match StoragePath::new(cfg, "virtual_display_map", StorageType::Other)
+        .and_then(|p| storage::read_file(&p))
+    {
+        Ok(Some(d)) => d,
+        Ok(None) => return Err(APIError::NotFound("VirtualDisplayMap".into())),
+        Err(e) => return Err(APIError::UnexpectedError(e)),
+    };
+
+    display::parse(&String::from_utf8_lossy(&data)).map_err(APIError::UnexpectedError)
+}
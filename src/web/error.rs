@@ -7,7 +7,9 @@ pub enum APIError {
     Ok,
     NotFound(String),
     BadRequest(String),
+    Unauthorized(String),
     Forbidden(String),
+    PayloadTooLarge(String),
     UnexpectedError(anyhow::Error),
 }
 
@@ -35,6 +37,14 @@ impl IntoResponse for APIError {
                 }),
             )
                 .into_response(),
+            APIError::Unauthorized(e) => (
+                StatusCode::UNAUTHORIZED,
+                Json(Status {
+                    status: "error".into(),
+                    error: Some(e),
+                }),
+            )
+                .into_response(),
             APIError::Forbidden(e) => (
                 StatusCode::FORBIDDEN,
                 Json(Status {
@@ -43,6 +53,14 @@ impl IntoResponse for APIError {
                 }),
             )
                 .into_response(),
+            APIError::PayloadTooLarge(e) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(Status {
+                    status: "error".into(),
+                    error: Some(e),
+                }),
+            )
+                .into_response(),
             APIError::UnexpectedError(e) => {
                 tracing::error!("An unexpected error occured: {e}");
                 (
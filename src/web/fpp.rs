@@ -3,6 +3,7 @@ use std::{path::Path, sync::Arc};
 use axum::{
     body::Bytes,
     extract,
+    http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
@@ -12,10 +13,10 @@ use rustix::path::Arg;
 use systemstat::{saturating_sub_bytes, Platform};
 
 use crate::{
-    built_info,
+    built_info, display, fpp,
     models::*,
     state::State,
-    storage,
+    storage::{self, StoragePath},
     web::{error::APIError, utils},
 };
 
@@ -54,10 +55,11 @@ pub async fn fpp_command(
                 }
             };
 
-            let path = Path::new(&state.cfg.storage)
-                .join(dir.to_string())
-                .join(&query.file);
-            if path.exists() {
+            let path = match StoragePath::new(&state.cfg, &query.file, dir) {
+                Ok(p) => p,
+                Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+            };
+            if path.path().exists() {
                 APIError::Ok.into_response()
             } else {
                 APIError::NotFound("File".into()).into_response()
@@ -67,6 +69,223 @@ pub async fn fpp_command(
     }
 }
 
+/// Run an FPP command
+///
+/// Run a command from the FPP command API. Only commands which
+/// control the player are supported, such as `Start Playlist`,
+/// `Effect Start`, and `Stop Now`.
+#[utoipa::path(
+    get,
+    path = "/api/command/{command}",
+    params(
+        ("command" = String, Path, description = "The FPP command", example = "Stop Now"),
+    ),
+    responses(
+        (status = 200, description = "Command run successfully", body = FppResponse),
+        (status = 404, description = "Unrecognized command", body = FppResponse),
+        (status = 500, description = "Something went wrong", body = FppResponse)
+    ),
+    tag = "FPP Compatibility"
+)]
+pub async fn run_command(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path(command): extract::Path<String>,
+) -> Response {
+    command_response(state, &command, &[]).await
+}
+
+/// Run an FPP command with arguments
+///
+/// Run a command from the FPP command API with the arguments
+/// supplied as additional path segments.
+#[utoipa::path(
+    get,
+    path = "/api/command/{command}/{args}",
+    params(
+        ("command" = String, Path, description = "The FPP command", example = "Start Playlist"),
+        ("args" = String, Path, description = "The command arguments", example = "playlist"),
+    ),
+    responses(
+        (status = 200, description = "Command run successfully", body = FppResponse),
+        (status = 404, description = "Unrecognized command", body = FppResponse),
+        (status = 500, description = "Something went wrong", body = FppResponse)
+    ),
+    tag = "FPP Compatibility"
+)]
+pub async fn run_command_args(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path((command, args)): extract::Path<(String, String)>,
+) -> Response {
+    let args = args.split('/').map(String::from).collect::<Vec<_>>();
+    command_response(state, &command, &args).await
+}
+
+/// Run an FPP command from JSON
+///
+/// Run a command from the FPP command API with the command
+/// and arguments supplied in the body.
+#[utoipa::path(
+    post,
+    path = "/api/command",
+    request_body(content = FppCommand),
+    responses(
+        (status = 200, description = "Command run successfully", body = FppResponse),
+        (status = 404, description = "Unrecognized command", body = FppResponse),
+        (status = 500, description = "Something went wrong", body = FppResponse)
+    ),
+    tag = "FPP Compatibility"
+)]
+pub async fn post_command(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    Json(command): Json<FppCommand>,
+) -> Response {
+    command_response(state, &command.command, &command.args).await
+}
+
+/// Start a playlist
+#[utoipa::path(
+    get,
+    path = "/api/playlist/{playlist}/start",
+    params(
+        ("playlist" = String, Path, description = "The name of the playlist")
+    ),
+    responses(
+        (status = 200, description = "Playlist started", body = FppResponse),
+        (status = 500, description = "Something went wrong", body = FppResponse)
+    ),
+    tag = "FPP Compatibility"
+)]
+pub async fn start_playlist(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path(playlist): extract::Path<String>,
+) -> Response {
+    player_response(state, PlayerState::Playlist(playlist), "Playlist started").await
+}
+
+/// Stop the current playlist
+#[utoipa::path(
+    get,
+    path = "/api/playlists/stop",
+    responses(
+        (status = 200, description = "Playlist stopped", body = FppResponse),
+        (status = 500, description = "Something went wrong", body = FppResponse)
+    ),
+    tag = "FPP Compatibility"
+)]
+pub async fn stop_playlist(extract::State(state): extract::State<Arc<Mutex<State>>>) -> Response {
+    player_response(state, PlayerState::Stop, "Playlist stopped").await
+}
+
+/// Get the FPP daemon status
+///
+/// Get the status of the player in the format FPP uses. Only
+/// the fields relevant to LEDPlayr are populated.
+#[utoipa::path(
+    get,
+    path = "/api/fppd/status",
+    responses(
+        (status = 200, description = "Status of the player", body = FppStatus)
+    ),
+    tag = "FPP Compatibility"
+)]
+pub async fn fppd_status(extract::State(state): extract::State<Arc<Mutex<State>>>) -> Response {
+    let info = state.lock().player.borrow().clone();
+
+    let (status, status_name) = match info.status {
+        PlayerStatus::Stopped => (0, "idle"),
+        _ => (1, "playing"),
+    };
+    let (mode, mode_name) = match info.status {
+        PlayerStatus::Bridge => (1, "bridge"),
+        _ => (2, "player"),
+    };
+
+    let elapsed = info.elapsed_ms / 1000;
+    let remaining = info.remaining_ms / 1000;
+
+    Json(FppStatus {
+        fppd: "running".into(),
+        mode,
+        mode_name: mode_name.into(),
+        status,
+        status_name: status_name.into(),
+        current_playlist: FppCurrentPlaylist {
+            count: info.playlist_count.to_string(),
+            index: match info.sequence {
+                Some(_) => (info.playlist_index + 1).to_string(),
+                None => "0".into(),
+            },
+            playlist: info.playlist.unwrap_or_default(),
+            playlist_type: match info.sequence {
+                Some(_) => "sequence".into(),
+                None => "".into(),
+            },
+            ..Default::default()
+        },
+        current_sequence: info.sequence.unwrap_or_default(),
+        seconds_played: elapsed.to_string(),
+        seconds_remaining: remaining.to_string(),
+        time_elapsed: format!("{:02}:{:02}", elapsed / 60, elapsed % 60),
+        time_remaining: format!("{:02}:{:02}", remaining / 60, remaining % 60),
+        next_playlist: match info.next_schedule {
+            Some(next) => FppNextPlaylist {
+                playlist: next.playlist,
+                start_time: next.start,
+            },
+            None => FppNextPlaylist {
+                playlist: "No playlist scheduled.".into(),
+                start_time: "".into(),
+            },
+        },
+        repeat_mode: "0".into(),
+        time: chrono::Local::now()
+            .format("%a %b %e %H:%M:%S %Z %Y")
+            .to_string(),
+        ..Default::default()
+    })
+    .into_response()
+}
+
+async fn command_response(state: Arc<Mutex<State>>, command: &str, args: &[String]) -> Response {
+    match fpp::player_state(command, args) {
+        Some(s) => player_response(state, s, "").await,
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(FppResponse {
+                status: "ERROR".into(),
+                message: format!("Unknown command: '{command}'"),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn player_response(state: Arc<Mutex<State>>, s: PlayerState, message: &str) -> Response {
+    let ctrl;
+    {
+        let state = state.lock();
+        ctrl = state.player_ctrl.clone();
+    }
+
+    if let Err(e) = ctrl.send(s).await {
+        tracing::error!("Could not run command: {e}");
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(FppResponse {
+                status: "ERROR".into(),
+                message: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    Json(FppResponse {
+        status: "OK".into(),
+        message: message.into(),
+    })
+    .into_response()
+}
+
 /// Get system info.
 ///
 /// Get the high-level system information. This endpoint is used
@@ -223,13 +442,15 @@ pub async fn get_display(extract::State(state): extract::State<Arc<Mutex<State>>
 
 /// Upload VirtualDisplayMap
 ///
-/// Upload the VirtualDisplayMap
+/// Upload the VirtualDisplayMap. The map is checked before it is
+/// stored and rejected if it can't be parsed.
 #[utoipa::path(
     post,
     path = "/api/configfile/virtualdisplaymap",
     request_body(content = inline(BinaryFile), content_type = "application/octet-stream"),
     responses(
         (status = 200, description = "VDM uploaded successfully", body = Status),
+        (status = 400, description = "VDM is invalid", body = Status),
         (status = 500, description = "Something went wrong", body = Status)
     ),
     tag = "FPP Compatibility"
@@ -238,6 +459,15 @@ pub async fn upload_display(
     extract::State(state): extract::State<Arc<Mutex<State>>>,
     data: Bytes,
 ) -> Response {
+    let contents = match std::str::from_utf8(&data) {
+        Ok(c) => c,
+        Err(e) => return APIError::BadRequest(format!("Invalid VDM: {e}")).into_response(),
+    };
+
+    if let Err(e) = display::parse(contents) {
+        return APIError::BadRequest(format!("Invalid VDM: {e}")).into_response();
+    }
+
     utils::upload_other(state, "virtual_display_map".into(), data.to_vec()).await
 }
 
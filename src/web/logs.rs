@@ -8,7 +8,7 @@ use axum::{
 };
 use parking_lot::Mutex;
 
-use crate::{models::Status, state::State, web::error::APIError};
+use crate::{models::Status, state::State, storage::StoragePath, web::error::APIError};
 
 /// Get log filenames
 #[utoipa::path(
@@ -78,7 +78,7 @@ pub async fn list_logs(extract::State(state): extract::State<Arc<Mutex<State>>>)
     ),
     responses(
         (status = 200, description = "List of log filenames", body = String),
-        (status = 400, description = "Logging is disabled", body = Status),
+        (status = 400, description = "Logging is disabled or the name is invalid", body = Status),
         (status = 500, description = "Something went wrong", body = Status)
     ),
     tag = "Logs"
@@ -91,7 +91,11 @@ pub async fn get_log(
 
     match &state.cfg.log {
         Some(log) => {
-            let path = std::path::Path::new(&log.directory).join(name);
+            let path = match StoragePath::within(std::path::Path::new(&log.directory), &name) {
+                Ok(p) => p,
+                Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+            };
+
             match std::fs::OpenOptions::new().read(true).open(path) {
                 Ok(mut logfile) => {
                     let mut data = String::new();
@@ -15,7 +15,7 @@ use crate::{
     },
     models::{BinaryFile, Status},
     state::State,
-    storage::{self, StorageType},
+    storage::{self, StoragePath, StorageType},
     web::error::APIError,
 };
 
@@ -51,6 +51,7 @@ pub async fn list_meshes(extract::State(state): extract::State<Arc<Mutex<State>>
     ),
     responses(
         (status = 200, description = "The requested mesh", body = inline(BinaryFile), content_type = "application/octet-stream"),
+        (status = 400, description = "Invalid mesh name", body = Status),
         (status = 404, description = "Unknown mesh", body = Status),
         (status = 500, description = "Something went wrong", body = Status)
     ),
@@ -62,7 +63,12 @@ pub async fn download_mesh(
 ) -> Response {
     let state = state.lock();
 
-    match storage::read_file(&state.cfg, &mesh_name, StorageType::Meshes) {
+    let path = match StoragePath::new(&state.cfg, &mesh_name, StorageType::Meshes) {
+        Ok(p) => p,
+        Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+    };
+
+    match storage::read_file(&path) {
         Ok(Some(d)) => ([(header::CONTENT_TYPE, "model/gltf-binary")], d).into_response(),
         Ok(None) => APIError::BadRequest("models.json not found".into()).into_response(),
         Err(e) => APIError::UnexpectedError(e).into_response(),
@@ -1,12 +1,16 @@
 pub mod router;
 
+mod auth;
 mod buttons;
 mod config;
+mod display;
 mod error;
 mod files;
 mod fpp;
 mod logs;
 mod meshes;
+mod outputs;
+mod overrides;
 mod player;
 mod playlists;
 mod scenes;
@@ -15,3 +19,6 @@ mod sequences;
 mod testing;
 mod upload;
 mod utils;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use axum::{
+    extract,
+    response::{IntoResponse, Response},
+    Json,
+};
+use parking_lot::Mutex;
+
+use crate::{
+    models::*,
+    state::State,
+    storage,
+    web::{error::APIError, utils},
+};
+
+/// Get the output settings
+///
+/// Get the colour order and gamma correction of each universe.
+/// Universes without settings are sent RGB with no correction.
+#[utoipa::path(
+    get,
+    path = "/api/outputs/settings",
+    responses(
+        (status = 200, description = "The settings of each universe", body = Vec<OutputSettings>),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Outputs"
+)]
+pub async fn get_output_settings(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+) -> Response {
+    let state = state.lock();
+
+    match storage::read_output_settings(&state.cfg) {
+        Ok(settings) => Json(settings).into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// Update the output settings
+///
+/// Replace the colour order and gamma correction of every universe.
+/// Changes are used the next time the player starts.
+#[utoipa::path(
+    put,
+    path = "/api/outputs/settings",
+    request_body(content = Vec<OutputSettings>),
+    responses(
+        (status = 200, description = "The settings were saved", body = Status),
+        (status = 400, description = "A gamma value is invalid", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Outputs"
+)]
+pub async fn update_output_settings(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    Json(settings): Json<Vec<OutputSettings>>,
+) -> Response {
+    if let Some(s) = settings
+        .iter()
+        .find(|s| s.gamma.is_some_and(|g| !(0.1..=5.0).contains(&g)))
+    {
+        return APIError::BadRequest(format!(
+            "Gamma of universe {} must be between 0.1 and 5",
+            s.id
+        ))
+        .into_response();
+    }
+
+    match serde_json::to_vec(&settings) {
+        Ok(data) => utils::upload_other(state, storage::OUTPUT_SETTINGS.into(), data).await,
+        Err(e) => APIError::UnexpectedError(e.into()).into_response(),
+    }
+}
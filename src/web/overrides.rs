@@ -0,0 +1,128 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use axum::{
+    extract,
+    response::{IntoResponse, Response},
+    Json,
+};
+use parking_lot::Mutex;
+
+use crate::{db, models::*, overlay, state::State, storage, web::error::APIError};
+
+/// Get the model overrides
+///
+/// Get the override of each model which has one, keyed by the name
+/// of the model.
+#[utoipa::path(
+    get,
+    path = "/api/overrides",
+    responses(
+        (status = 200, description = "The override of each model", body = BTreeMap<String, Override>),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Overrides"
+)]
+pub async fn list_overrides(extract::State(state): extract::State<Arc<Mutex<State>>>) -> Response {
+    let overrides = state.lock().overrides.borrow().clone();
+
+    Json(overrides).into_response()
+}
+
+/// Override a model
+///
+/// Force a model to a colour, off, or a brightness on top of whatever
+/// is playing. The override applies straight away and is kept until
+/// it is cleared.
+#[utoipa::path(
+    put,
+    path = "/api/override/{model}",
+    params(
+        ("model" = String, Path, description = "The name of the model")
+    ),
+    request_body(content = Override),
+    responses(
+        (status = 200, description = "The model was overridden", body = Status),
+        (status = 400, description = "Brightness is more than 100%", body = Status),
+        (status = 404, description = "The model wasn't found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Overrides"
+)]
+pub async fn set_override(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path(model): extract::Path<String>,
+    Json(o): Json<Override>,
+) -> Response {
+    if matches!(o, Override::Brightness(b) if b > 100) {
+        return APIError::BadRequest("Brightness must be between 0 and 100".into()).into_response();
+    }
+
+    let mut state = state.lock();
+
+    match storage::read_models(&state.cfg) {
+        Ok(models) if models.iter().any(|m| m.name == model) => {}
+        Ok(_) => return APIError::NotFound("Model".into()).into_response(),
+        Err(e) => return APIError::UnexpectedError(e).into_response(),
+    }
+
+    let mut overrides = state.overrides.borrow().clone();
+    overrides.insert(model, o);
+
+    save_overrides(&mut state, overrides)
+}
+
+/// Clear a model's override
+#[utoipa::path(
+    delete,
+    path = "/api/override/{model}",
+    params(
+        ("model" = String, Path, description = "The name of the model")
+    ),
+    responses(
+        (status = 200, description = "The override was cleared", body = Status),
+        (status = 404, description = "The model isn't overridden", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Overrides"
+)]
+pub async fn del_override(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path(model): extract::Path<String>,
+) -> Response {
+    let mut state = state.lock();
+
+    let mut overrides = state.overrides.borrow().clone();
+    if overrides.remove(&model).is_none() {
+        return APIError::NotFound("Override".into()).into_response();
+    }
+
+    save_overrides(&mut state, overrides)
+}
+
+/// Clear every model's override
+#[utoipa::path(
+    delete,
+    path = "/api/overrides",
+    responses(
+        (status = 200, description = "The overrides were cleared", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Overrides"
+)]
+pub async fn del_overrides(extract::State(state): extract::State<Arc<Mutex<State>>>) -> Response {
+    save_overrides(&mut state.lock(), BTreeMap::new())
+}
+
+fn save_overrides(state: &mut State, overrides: BTreeMap<String, Override>) -> Response {
+    let value = match serde_json::to_string(&overrides) {
+        Ok(v) => v,
+        Err(e) => return APIError::UnexpectedError(e.into()).into_response(),
+    };
+
+    if let Err(e) = db::set_setting(&mut state.db_conn, overlay::SETTING, value) {
+        return APIError::UnexpectedError(e).into_response();
+    }
+    state.overrides.send_replace(overrides);
+
+    APIError::Ok.into_response()
+}
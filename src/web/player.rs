@@ -1,28 +1,269 @@
-use std::sync::Arc;
+use std::{io::BufWriter, ops::Range, sync::Arc, time::Duration};
 
 use axum::{
-    extract,
-    response::{IntoResponse, Response},
-    Json,
+    extract::{
+        self,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Extension, Json,
 };
+use futures_util::{stream, StreamExt};
 use parking_lot::Mutex;
+use tokio::{
+    sync::{
+        broadcast::{
+            self,
+            error::{RecvError, TryRecvError},
+        },
+        mpsc,
+    },
+    time::MissedTickBehavior,
+};
+use tokio_util::sync::CancellationToken;
 
-use crate::{models::*, patterns::TestSpec, state::State, web::error::APIError};
+use crate::{
+    db, dimmer,
+    fseq::writer::Writer,
+    models::*,
+    patterns::TestSpec,
+    player::Data,
+    state::State,
+    storage::{self, StoragePath},
+    web::{error::APIError, utils},
+};
 
 /// Get the player status
+///
+/// Get the detailed status of the player, including what is
+/// playing, the progress through it, the next schedule due to
+/// start, and the last error the player reported.
 #[utoipa::path(
     get,
     path = "/api/player",
     responses(
-        (status = 200, description = "Status of the scheduler", body = PlayerStatus),
+        (status = 200, description = "Status of the player", body = PlayerInfo),
         (status = 500, description = "Something went wrong", body = Status)
     ),
     tag = "Player"
 )]
 pub async fn get_status(extract::State(state): extract::State<Arc<Mutex<State>>>) -> Response {
-    let state = state.lock();
+    let info = state.lock().player.borrow().clone();
+
+    Json(info).into_response()
+}
+
+/// Stream the player status
+///
+/// Stream the detailed player status as Server-Sent Events. An
+/// event is sent whenever the status changes and at least once
+/// per second to track the progress of the current sequence.
+#[utoipa::path(
+    get,
+    path = "/api/player/events",
+    responses(
+        (status = 200, description = "Stream of player statuses", body = PlayerInfo, content_type = "text/event-stream")
+    ),
+    tag = "Player"
+)]
+pub async fn status_events(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    Extension(cancel): Extension<CancellationToken>,
+) -> Response {
+    let rx = state.lock().player.subscribe();
+
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let events = stream::unfold((rx, interval), |(mut rx, mut interval)| async move {
+        tokio::select! {
+            res = rx.changed() => res.ok()?,
+            _ = interval.tick() => {},
+        }
+
+        let event = Event::default()
+            .event("status")
+            .json_data(rx.borrow_and_update().clone());
+        Some((event, (rx, interval)))
+    })
+    .take_until(cancel.cancelled_owned());
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Stream a live preview of the output
+///
+/// Stream the channel data the player is sending over a WebSocket.
+/// Each binary message holds a whole frame of channel data starting
+/// at the first channel of the display, or of the model if one is
+/// given. Frames are only sent when something changed, at most `fps`
+/// times per second (default 25, at most 50).
+#[utoipa::path(
+    get,
+    path = "/api/player/preview",
+    params(PreviewQuery),
+    responses(
+        (status = 101, description = "Switching to the WebSocket protocol"),
+        (status = 404, description = "Model not found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Player"
+)]
+pub async fn preview(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    Extension(cancel): Extension<CancellationToken>,
+    extract::Query(query): extract::Query<PreviewQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let (rx, range) = {
+        let state = state.lock();
+
+        let range = match &query.model {
+            Some(name) => match storage::read_models(&state.cfg) {
+                Ok(models) => match models.into_iter().find(|m| &m.name == name) {
+                    Some(m) => {
+                        let start = m.start_channel.saturating_sub(1) as usize;
+                        Some(start..start + m.channel_count as usize)
+                    }
+                    None => {
+                        return APIError::NotFound(format!("Model not found: {name}"))
+                            .into_response()
+                    }
+                },
+                Err(e) => return APIError::UnexpectedError(e).into_response(),
+            },
+            None => None,
+        };
+
+        (state.frames.subscribe(), range)
+    };
+
+    let fps = query.fps.unwrap_or(25).clamp(1, 50);
+
+    ws.on_upgrade(move |socket| stream_preview(socket, rx, range, fps, cancel))
+}
+
+async fn stream_preview(
+    mut socket: WebSocket,
+    mut rx: broadcast::Receiver<Data>,
+    range: Option<Range<usize>>,
+    fps: u32,
+    cancel: CancellationToken,
+) {
+    let mut frame = match &range {
+        Some(range) => vec![0; range.len()],
+        None => Vec::new(),
+    };
+    let mut dirty = false;
+
+    let mut interval = tokio::time::interval(Duration::from_secs(1) / fps);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                _ => {}
+            },
+            data = rx.recv() => match data {
+                Ok(data) => dirty |= apply_frame(&mut frame, &data, range.as_ref()),
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            },
+            _ = interval.tick(), if dirty => {
+                if socket.send(Message::Binary(frame.clone().into())).await.is_err() {
+                    break;
+                }
+                dirty = false;
+            }
+        }
+    }
+}
+
+/// Copy the channel data into the preview frame, returning whether
+/// any of it was in range
+fn apply_frame(frame: &mut Vec<u8>, data: &Data, range: Option<&Range<usize>>) -> bool {
+    let end = data.offset + data.data.len();
+
+    match range {
+        Some(range) => {
+            let start = data.offset.max(range.start);
+            let stop = end.min(range.end);
+            if start >= stop {
+                return false;
+            }
+
+            frame[start - range.start..stop - range.start]
+                .copy_from_slice(&data.data[start - data.offset..stop - data.offset]);
+        }
+        None => {
+            if frame.len() < end {
+                frame.resize(end, 0);
+            }
+            frame[data.offset..end].copy_from_slice(&data.data);
+        }
+    }
+
+    true
+}
+
+/// Get the master brightness
+#[utoipa::path(
+    get,
+    path = "/api/player/brightness",
+    responses(
+        (status = 200, description = "The master brightness", body = Brightness),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Player"
+)]
+pub async fn get_brightness(extract::State(state): extract::State<Arc<Mutex<State>>>) -> Response {
+    let brightness = *state.lock().brightness.borrow();
+
+    Json(Brightness { brightness }).into_response()
+}
+
+/// Set the master brightness
+///
+/// Dim everything the player outputs. The change applies to whatever
+/// is playing straight away and is kept across restarts.
+#[utoipa::path(
+    put,
+    path = "/api/player/brightness",
+    request_body(content = Brightness),
+    responses(
+        (status = 200, description = "Brightness set ok", body = Status),
+        (status = 400, description = "Brightness is more than 100%", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Player"
+)]
+pub async fn set_brightness(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    Json(brightness): Json<Brightness>,
+) -> Response {
+    if brightness.brightness > 100 {
+        return APIError::BadRequest("Brightness must be between 0 and 100".into()).into_response();
+    }
+
+    let mut state = state.lock();
+
+    if let Err(e) = db::set_setting(
+        &mut state.db_conn,
+        dimmer::MASTER_SETTING,
+        brightness.brightness.to_string(),
+    ) {
+        return APIError::UnexpectedError(e).into_response();
+    }
+    state.brightness.send_replace(brightness.brightness);
 
-    Json(state.player_status.clone()).into_response()
+    APIError::Ok.into_response()
 }
 
 /// Start the player scheduling
@@ -50,7 +291,39 @@ pub async fn start_scheduler(extract::State(state): extract::State<Arc<Mutex<Sta
     APIError::Ok.into_response()
 }
 
+/// Bridge DDP and E1.31 to the controllers
+///
+/// Forward channel data received from xLights, or anything else
+/// sending DDP or E1.31, to the controllers until the player is
+/// stopped.
+#[utoipa::path(
+    get,
+    path = "/api/player/bridge",
+    responses(
+        (status = 200, description = "Bridge started ok", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Player"
+)]
+pub async fn start_bridge(extract::State(state): extract::State<Arc<Mutex<State>>>) -> Response {
+    let ctrl;
+    {
+        let state = state.lock();
+        ctrl = state.player_ctrl.clone();
+    }
+
+    if let Err(e) = ctrl.send(PlayerState::Bridge).await {
+        tracing::error!("Could not start bridge: {e}");
+        return APIError::UnexpectedError(e.into()).into_response();
+    }
+
+    APIError::Ok.into_response()
+}
+
 /// Stop the player
+///
+/// Stopping a test goes back to the scheduler if it was running before
+/// the test, stop again to stop the scheduler too.
 #[utoipa::path(
     get,
     path = "/api/player/stop",
@@ -76,6 +349,10 @@ pub async fn stop(extract::State(state): extract::State<Arc<Mutex<State>>>) -> R
 }
 
 /// Run LED test patterns
+///
+/// Tests with a duration stop by themselves. Once a test stops, by
+/// itself or when the player is stopped, the scheduler is started again
+/// if it was running before.
 #[utoipa::path(
     post,
     path = "/api/player/test",
@@ -103,3 +380,152 @@ pub async fn run_test(
 
     APIError::Ok.into_response()
 }
+
+/// Default time between recorded frames
+const RECORD_STEP_MS: u8 = 50;
+
+/// Record the player's output
+///
+/// Save everything sent to the controllers for a length of time as a
+/// new sequence, whether it comes from a playlist, a test or the
+/// bridge, including any model overrides. The recording carries on in the background, the player
+/// status shows the sequence being recorded until it is finished.
+#[utoipa::path(
+    post,
+    path = "/api/player/record",
+    request_body(content = RecordSpec),
+    responses(
+        (status = 200, description = "Recording started ok", body = Status),
+        (status = 400, description = "The name or length is invalid, or already recording", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Player"
+)]
+pub async fn start_recording(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    Json(spec): Json<RecordSpec>,
+) -> Response {
+    let step_ms = spec.step_ms.unwrap_or(RECORD_STEP_MS);
+    if step_ms == 0 || spec.duration_ms == 0 {
+        return APIError::BadRequest("The duration and step must be more than 0".into())
+            .into_response();
+    }
+    let Ok(frames) = u32::try_from(spec.duration_ms.div_ceil(step_ms as u64)) else {
+        return APIError::BadRequest("The recording is too long".into()).into_response();
+    };
+
+    let (info, path, channels) = {
+        let state = state.lock();
+        let info = state.player.clone();
+
+        if let Some(name) = &info.borrow().recording {
+            return APIError::BadRequest(format!("Already recording {name}")).into_response();
+        }
+
+        let path = match utils::new_sequence_path(&state, &spec.name) {
+            Ok(p) => p,
+            Err(e) => return e.into_response(),
+        };
+
+        // Record every channel of the outputs
+        let channels = match storage::read_outputs(&state.cfg) {
+            Ok(outputs) => outputs
+                .channel_outputs
+                .iter()
+                .flat_map(|c| c.universes.iter())
+                .map(|u| (u.start_channel + u.channel_count).saturating_sub(1) as usize)
+                .max()
+                .unwrap_or(0),
+            Err(e) => return APIError::UnexpectedError(e).into_response(),
+        };
+        if channels == 0 {
+            return APIError::BadRequest("There are no outputs to record".into()).into_response();
+        }
+
+        info.send_modify(|p| p.recording = Some(spec.name.clone()));
+
+        (info, path, channels)
+    };
+
+    tracing::info!("Recording {} frames to {}", frames, spec.name);
+
+    tokio::spawn(async move {
+        match record(state, path, channels, frames, step_ms).await {
+            Ok(_) => tracing::info!("Finished recording {}", spec.name),
+            Err(e) => {
+                let e = format!("Error recording {}: {e:#}", spec.name);
+                tracing::error!("{e}");
+                info.send_modify(|p| p.last_error = Some(e));
+            }
+        }
+        info.send_modify(|p| p.recording = None);
+    });
+
+    APIError::Ok.into_response()
+}
+
+/// Record everything the player sends as a new sequence
+///
+/// Channels keep their last value until they are sent again, so the
+/// recording holds whatever was on the display at every step whether
+/// it came from a playlist, a test or the bridge. Channels are
+/// recorded after dimming and model overrides but before the colour
+/// order and gamma of the outputs are applied, like any other sequence.
+async fn record(
+    state: Arc<Mutex<State>>,
+    path: StoragePath,
+    channels: usize,
+    frames: u32,
+    step_ms: u8,
+) -> anyhow::Result<()> {
+    let mut player = state.lock().frames.subscribe();
+    let (tx, mut rx) = mpsc::channel::<Vec<u8>>(16);
+
+    let writing = utils::write_sequence(state, path, move |f| {
+        let mut w = Writer::new(
+            BufWriter::new(f),
+            channels as u32,
+            frames,
+            step_ms,
+            &[],
+            &[],
+        )?;
+        while let Some(frame) = rx.blocking_recv() {
+            w.write_frame(&frame)?;
+        }
+        w.finish()?;
+        Ok(())
+    });
+
+    let sampling = async move {
+        let mut frame = vec![0u8; channels];
+        let mut intvl = tokio::time::interval(Duration::from_millis(step_ms as u64));
+
+        for _ in 0..frames {
+            intvl.tick().await;
+
+            // Apply everything sent since the last step
+            loop {
+                match player.try_recv() {
+                    Ok(data) => {
+                        let end = (data.offset + data.data.len()).min(channels);
+                        if data.offset < end {
+                            frame[data.offset..end]
+                                .copy_from_slice(&data.data[..end - data.offset]);
+                        }
+                    }
+                    Err(TryRecvError::Lagged(n)) => tracing::warn!("Recording missed {n} frames"),
+                    Err(_) => break,
+                }
+            }
+
+            if tx.send(frame.clone()).await.is_err() {
+                // The writer has failed
+                break;
+            }
+        }
+    };
+
+    let (res, _) = tokio::join!(writing, sampling);
+    res
+}
@@ -110,6 +110,7 @@ pub async fn get_playlist(
                         play_once: s.play_once,
                         enabled: s.enabled,
                         playlist_type: "sequence".into(),
+                        brightness: Some(s.brightness as u8),
                     }
                 })
                 .collect();
@@ -228,6 +229,7 @@ async fn create_or_update_playlist(state: Arc<Mutex<State>>, playlist: Playlist)
         .map(|s| NewSequencePlus {
             enabled: s.enabled,
             play_once: s.play_once,
+            brightness: s.brightness.unwrap_or(100).min(100) as i32,
             sequence: s.sequence_name,
         })
         .collect();
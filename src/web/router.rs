@@ -1,9 +1,14 @@
-use std::{net::Ipv4Addr, sync::Arc};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
 
 use anyhow::Context;
 use axum::{
-    routing::{get, post},
-    Router,
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post, put},
+    Extension, Router,
 };
 use parking_lot::Mutex;
 use tokio_util::sync::CancellationToken;
@@ -16,8 +21,8 @@ use utoipauto::utoipauto;
 use crate::{
     state::State,
     web::{
-        buttons, config, files, fpp, logs, meshes, player, playlists, scenes, schedules, sequences,
-        testing, upload,
+        auth, buttons, config, display, files, fpp, logs, meshes, outputs, overrides, player,
+        playlists, scenes, schedules, sequences, testing, upload,
     },
 };
 
@@ -54,10 +59,24 @@ pub async fn run_server(state: Arc<Mutex<State>>, cancel: CancellationToken) {
 
     let app = Router::new()
         .merge(RapiDoc::with_openapi("/api-docs/openapi2.json", ApiDoc::openapi()).path("/rapidoc"))
-        .route("/jqupload.php", post(upload::file_upload))
-        .route("/api/upload", post(upload::file_upload))
+        // Uploads enforce their own limit as they're streamed to disk
+        .route(
+            "/jqupload.php",
+            post(upload::file_upload).layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/api/upload",
+            post(upload::file_upload).layer(DefaultBodyLimit::disable()),
+        )
         .route("/fppxml.php", get(fpp::fpp_command))
+        .route("/api/command", post(fpp::post_command))
+        .route("/api/command/{command}", get(fpp::run_command))
+        .route("/api/command/{command}/{*args}", get(fpp::run_command_args))
+        .route("/api/fppd/status", get(fpp::fppd_status))
         .route("/api/sequences", get(sequences::list_sequences))
+        .route("/api/sequences/search", get(sequences::search_sequences))
+        .route("/api/sequences/tags", get(sequences::list_tags))
+        .route("/api/sequences/concat", post(sequences::concat_sequences))
         .route(
             "/api/sequence/{filename}",
             get(sequences::get_sequence).delete(sequences::del_sequence),
@@ -66,11 +85,36 @@ pub async fn run_server(state: Arc<Mutex<State>>, cancel: CancellationToken) {
             "/api/sequence/{filename}/meta",
             get(sequences::get_sequence_meta),
         )
+        .route(
+            "/api/sequence/{filename}/render",
+            get(sequences::render_sequence),
+        )
+        .route(
+            "/api/sequence/{filename}/brightness",
+            put(sequences::set_sequence_brightness),
+        )
+        .route(
+            "/api/sequence/{filename}/rename",
+            post(sequences::rename_sequence),
+        )
+        .route(
+            "/api/sequence/{filename}/copy",
+            post(sequences::copy_sequence),
+        )
+        .route(
+            "/api/sequence/{filename}/tags",
+            put(sequences::set_sequence_tags),
+        )
+        .route(
+            "/api/sequence/{filename}/trim",
+            post(sequences::trim_sequence),
+        )
         .route("/api/playlists", get(playlists::list_playlists))
         .route(
             "/api/playlists/numbered",
             get(playlists::list_playlists_numbered),
         )
+        .route("/api/playlists/stop", get(fpp::stop_playlist))
         .route("/api/playlist", post(playlists::new_playlist))
         .route(
             "/api/playlist/{playlist}",
@@ -79,6 +123,7 @@ pub async fn run_server(state: Arc<Mutex<State>>, cancel: CancellationToken) {
                 .put(playlists::update_playlist)
                 .delete(playlists::del_playlist),
         )
+        .route("/api/playlist/{playlist}/start", get(fpp::start_playlist))
         .route("/api/schedules", get(schedules::list_schedules))
         .route("/api/schedule", post(schedules::new_schedule))
         .route(
@@ -96,14 +141,35 @@ pub async fn run_server(state: Arc<Mutex<State>>, cancel: CancellationToken) {
             "/api/configfile/virtualdisplaymap",
             get(fpp::get_display).post(fpp::upload_display),
         )
+        .route("/api/display", get(display::get_virtual_display))
         .route(
             "/api/channel/output/universeOutputs",
             get(fpp::get_outputs).post(fpp::upload_outputs),
         )
+        .route(
+            "/api/outputs/settings",
+            get(outputs::get_output_settings).put(outputs::update_output_settings),
+        )
+        .route(
+            "/api/overrides",
+            get(overrides::list_overrides).delete(overrides::del_overrides),
+        )
+        .route(
+            "/api/override/{model}",
+            put(overrides::set_override).delete(overrides::del_override),
+        )
         .route("/api/player", get(player::get_status))
+        .route("/api/player/events", get(player::status_events))
+        .route("/api/player/preview", get(player::preview))
+        .route(
+            "/api/player/brightness",
+            get(player::get_brightness).put(player::set_brightness),
+        )
         .route("/api/player/schedule", get(player::start_scheduler))
+        .route("/api/player/bridge", get(player::start_bridge))
         .route("/api/player/stop", get(player::stop))
         .route("/api/player/test", post(player::run_test))
+        .route("/api/player/record", post(player::start_recording))
         .route("/api/test_pattern", post(testing::get_test_pattern))
         .route("/api/logs", get(logs::list_logs))
         .route("/api/log/{name}", get(logs::get_log))
@@ -136,7 +202,24 @@ pub async fn run_server(state: Arc<Mutex<State>>, cancel: CancellationToken) {
             "/api/config/timezone",
             get(config::get_current_time_and_timezone).post(config::set_timezone),
         )
+        .route("/api/auth/login", post(auth::login))
+        .route("/api/auth/logout", post(auth::logout))
+        .route("/api/auth/me", get(auth::current_user))
+        .route("/api/users", get(auth::list_users))
+        .route("/api/user", post(auth::new_user))
+        .route(
+            "/api/user/{username}",
+            put(auth::update_user).delete(auth::del_user),
+        )
+        .route("/api/tokens", get(auth::list_tokens))
+        .route("/api/token", post(auth::new_token))
+        .route("/api/token/{name}", delete(auth::del_token))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::authorize,
+        ))
         .fallback(files::static_handler)
+        .layer(Extension(cancel.clone()))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(trace::DefaultMakeSpan::new().level(Level::DEBUG))
@@ -144,9 +227,12 @@ pub async fn run_server(state: Arc<Mutex<State>>, cancel: CancellationToken) {
         )
         .with_state(state.clone());
 
-    match axum::serve(listener, app)
-        .with_graceful_shutdown(async move { cancel.cancelled().await })
-        .await
+    match axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move { cancel.cancelled().await })
+    .await
     {
         Ok(_) => {}
         Err(e) => tracing::error!("Axum exited with an error: {}", e),
@@ -1,13 +1,20 @@
-use std::sync::Arc;
+use std::{io::BufWriter, sync::Arc};
 
 use axum::{
     extract,
     response::{IntoResponse, Response},
     Json,
 };
+use base64::Engine;
 use parking_lot::Mutex;
 
-use crate::{db, models::*, state::State, storage, web::error::APIError};
+use crate::{
+    db, fseq,
+    models::*,
+    state::State,
+    storage::{self, StoragePath, StorageType},
+    web::{display, error::APIError, utils},
+};
 
 /// List all sequences
 ///
@@ -41,6 +48,7 @@ pub async fn list_sequences(extract::State(state): extract::State<Arc<Mutex<Stat
     ),
     responses(
         (status = 200, description = "The requested sequence", body = inline(BinaryFile), content_type = "application/octet-stream"),
+        (status = 400, description = "Invalid filename", body = Status),
         (status = 404, description = "The sequence wasn't found", body = Status),
         (status = 500, description = "Something went wrong", body = Status)
     ),
@@ -52,7 +60,12 @@ pub async fn get_sequence(
 ) -> Response {
     let state = state.lock();
 
-    match storage::read_file(&state.cfg, &filename, storage::StorageType::Sequences) {
+    let path = match StoragePath::new(&state.cfg, &filename, StorageType::Sequences) {
+        Ok(p) => p,
+        Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+    };
+
+    match storage::read_file(&path) {
         Ok(Some(data)) => data.into_response(),
         Ok(None) => APIError::NotFound("Sequence".into()).into_response(),
         Err(e) => APIError::UnexpectedError(e).into_response(),
@@ -70,6 +83,7 @@ pub async fn get_sequence(
     ),
     responses(
         (status = 200, description = "The sequence was removed", body = Status),
+        (status = 400, description = "Invalid filename", body = Status),
         (status = 500, description = "Something went wrong", body = Status)
     ),
     tag = "Sequences"
@@ -80,7 +94,12 @@ pub async fn del_sequence(
 ) -> Response {
     let mut state = state.lock();
 
-    if let Err(e) = storage::del_file(&state.cfg, &filename, storage::StorageType::Sequences) {
+    let path = match StoragePath::new(&state.cfg, &filename, StorageType::Sequences) {
+        Ok(p) => p,
+        Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+    };
+
+    if let Err(e) = storage::del_file(&path) {
         return APIError::UnexpectedError(e).into_response();
     }
 
@@ -91,6 +110,378 @@ pub async fn del_sequence(
     }
 }
 
+/// Search sequences
+///
+/// Find sequences by name, FSEQ variables, or tag
+#[utoipa::path(
+    get,
+    path = "/api/sequences/search",
+    params(SequenceSearch),
+    responses(
+        (status = 200, description = "The matching sequences", body = Vec<SequenceDetails>),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Sequences"
+)]
+pub async fn search_sequences(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Query(query): extract::Query<SequenceSearch>,
+) -> Response {
+    let mut state = state.lock();
+
+    match db::search_sequences(
+        &mut state.db_conn,
+        query.q,
+        query.tag,
+        query.sort.unwrap_or_default(),
+        query.desc.unwrap_or(false),
+    ) {
+        Ok(seqs) => Json(
+            seqs.into_iter()
+                .map(|(seq, tags)| SequenceDetails {
+                    name: seq.name,
+                    channels: seq.channels as u32,
+                    frames: seq.frames as u32,
+                    duration_ms: seq.frames as u64 * seq.step_time as u64,
+                    uploaded: seq.uploaded,
+                    brightness: seq.brightness as u8,
+                    tags,
+                })
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// List tags
+///
+/// List every tag given to a sequence
+#[utoipa::path(
+    get,
+    path = "/api/sequences/tags",
+    responses(
+        (status = 200, description = "The tags in use", body = Vec<String>),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Sequences"
+)]
+pub async fn list_tags(extract::State(state): extract::State<Arc<Mutex<State>>>) -> Response {
+    let mut state = state.lock();
+
+    match db::get_tags(&mut state.db_conn) {
+        Ok(tags) => Json(tags).into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// Tag a sequence
+///
+/// Replace the tags of a sequence
+#[utoipa::path(
+    put,
+    path = "/api/sequence/{filename}/tags",
+    params(
+        ("filename" = String, Path, description = "The sequence to tag")
+    ),
+    request_body(content = Vec<String>),
+    responses(
+        (status = 200, description = "The tags were updated", body = Status),
+        (status = 404, description = "The sequence wasn't found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Sequences"
+)]
+pub async fn set_sequence_tags(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path(filename): extract::Path<String>,
+    Json(tags): Json<Vec<String>>,
+) -> Response {
+    let mut state = state.lock();
+
+    let mut tags = tags
+        .into_iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>();
+    tags.sort();
+    tags.dedup();
+
+    match db::set_sequence_tags(&mut state.db_conn, filename, tags) {
+        Ok(Some(_)) => APIError::Ok.into_response(),
+        Ok(None) => APIError::NotFound("Sequence".into()).into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// Set a sequence's brightness
+///
+/// Dim a sequence without editing it. This is combined with the
+/// master brightness and that of the playlist entry.
+#[utoipa::path(
+    put,
+    path = "/api/sequence/{filename}/brightness",
+    params(
+        ("filename" = String, Path, description = "The sequence to dim")
+    ),
+    request_body(content = Brightness),
+    responses(
+        (status = 200, description = "The brightness was updated", body = Status),
+        (status = 400, description = "Brightness is more than 100%", body = Status),
+        (status = 404, description = "The sequence wasn't found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Sequences"
+)]
+pub async fn set_sequence_brightness(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path(filename): extract::Path<String>,
+    Json(brightness): Json<Brightness>,
+) -> Response {
+    if brightness.brightness > 100 {
+        return APIError::BadRequest("Brightness must be between 0 and 100".into()).into_response();
+    }
+
+    let mut state = state.lock();
+
+    match db::set_sequence_brightness(&mut state.db_conn, filename, brightness.brightness as i32) {
+        Ok(Some(_)) => APIError::Ok.into_response(),
+        Ok(None) => APIError::NotFound("Sequence".into()).into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// Rename a sequence
+///
+/// Rename a sequence file. Playlists and buttons using the sequence
+/// are updated to use the new name.
+#[utoipa::path(
+    post,
+    path = "/api/sequence/{filename}/rename",
+    params(
+        ("filename" = String, Path, description = "The sequence to rename")
+    ),
+    request_body(content = RenameSequence),
+    responses(
+        (status = 200, description = "The sequence was renamed", body = Status),
+        (status = 400, description = "The new name is invalid or already used", body = Status),
+        (status = 404, description = "The sequence wasn't found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Sequences"
+)]
+pub async fn rename_sequence(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path(filename): extract::Path<String>,
+    Json(rename): Json<RenameSequence>,
+) -> Response {
+    let mut state = state.lock();
+
+    if filename == rename.name {
+        return APIError::Ok.into_response();
+    }
+
+    let from = match StoragePath::new(&state.cfg, &filename, StorageType::Sequences) {
+        Ok(p) => p,
+        Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+    };
+    let to = match utils::new_sequence_path(&state, &rename.name) {
+        Ok(p) => p,
+        Err(e) => return e.into_response(),
+    };
+
+    match db::rename_sequence(&mut state.db_conn, filename, rename.name, || {
+        std::fs::rename(&from, &to).map_err(|e| anyhow::anyhow!(e))
+    }) {
+        Ok(Some(_)) => APIError::Ok.into_response(),
+        Ok(None) => APIError::NotFound("Sequence".into()).into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// Copy a sequence
+///
+/// Copy a sequence file to a new name. The copy is added as if it had
+/// been uploaded and has the same tags.
+#[utoipa::path(
+    post,
+    path = "/api/sequence/{filename}/copy",
+    params(
+        ("filename" = String, Path, description = "The sequence to copy")
+    ),
+    request_body(content = CopySequence),
+    responses(
+        (status = 200, description = "The sequence was copied", body = Status),
+        (status = 400, description = "The new name is invalid or already used", body = Status),
+        (status = 404, description = "The sequence wasn't found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Sequences"
+)]
+pub async fn copy_sequence(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path(filename): extract::Path<String>,
+    Json(copy): Json<CopySequence>,
+) -> Response {
+    let (from, to, tags) = {
+        let mut state = state.lock();
+
+        let from = match StoragePath::new(&state.cfg, &filename, StorageType::Sequences) {
+            Ok(p) => p,
+            Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+        };
+        let tags = match db::get_sequence(&mut state.db_conn, filename) {
+            Ok(Some((seq, _))) => match db::get_sequence_tags(&mut state.db_conn, &seq) {
+                Ok(t) => t,
+                Err(e) => return APIError::UnexpectedError(e).into_response(),
+            },
+            Ok(None) => return APIError::NotFound("Sequence".into()).into_response(),
+            Err(e) => return APIError::UnexpectedError(e).into_response(),
+        };
+
+        match utils::new_sequence_path(&state, &copy.name) {
+            Ok(p) => (from, p, tags),
+            Err(e) => return e.into_response(),
+        }
+    };
+
+    let res = utils::write_sequence(state.clone(), to, move |f| {
+        let mut src = std::fs::File::open(&from)?;
+        std::io::copy(&mut src, f)?;
+        Ok(())
+    })
+    .await;
+    if let Err(e) = res {
+        return APIError::UnexpectedError(e).into_response();
+    }
+
+    let mut state = state.lock();
+    match db::set_sequence_tags(&mut state.db_conn, copy.name, tags) {
+        Ok(_) => APIError::Ok.into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
+/// Trim a sequence
+///
+/// Create a new sequence from part of an existing one
+#[utoipa::path(
+    post,
+    path = "/api/sequence/{filename}/trim",
+    params(
+        ("filename" = String, Path, description = "The sequence to trim")
+    ),
+    request_body(content = TrimSequence),
+    responses(
+        (status = 200, description = "The new sequence was created", body = Status),
+        (status = 400, description = "The name or time range is invalid", body = Status),
+        (status = 404, description = "The sequence wasn't found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Sequences"
+)]
+pub async fn trim_sequence(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path(filename): extract::Path<String>,
+    Json(trim): Json<TrimSequence>,
+) -> Response {
+    let (mut seq, path) = {
+        let state = state.lock();
+
+        let seq = match StoragePath::new(&state.cfg, &filename, StorageType::Sequences)
+            .and_then(|p| storage::read_sequence_meta(&p))
+        {
+            Ok(Some(seq)) => seq,
+            Ok(None) => return APIError::NotFound("Sequence".into()).into_response(),
+            Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+        };
+
+        match utils::new_sequence_path(&state, &trim.name) {
+            Ok(p) => (seq, p),
+            Err(e) => return e.into_response(),
+        }
+    };
+
+    let step_ms = seq.step_time_ms.max(1) as u64;
+    let start = trim.start_ms.unwrap_or(0) / step_ms;
+    let end = trim
+        .end_ms
+        .map(|end| end / step_ms)
+        .unwrap_or(u64::MAX)
+        .min(seq.frame_count as u64);
+
+    if start >= end {
+        return APIError::BadRequest("Invalid time range".into()).into_response();
+    }
+
+    create_sequence(state, path, move |f| {
+        fseq::writer::slice(&mut seq, start as u32, end as u32, BufWriter::new(f)).map(|_| ())
+    })
+    .await
+}
+
+/// Join sequences
+///
+/// Create a new sequence by playing sequences one after another. The
+/// sequences must have the same channels and step time.
+#[utoipa::path(
+    post,
+    path = "/api/sequences/concat",
+    request_body(content = ConcatSequences),
+    responses(
+        (status = 200, description = "The new sequence was created", body = Status),
+        (status = 400, description = "The name is invalid or the sequences don't match", body = Status),
+        (status = 404, description = "A sequence wasn't found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Sequences"
+)]
+pub async fn concat_sequences(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    Json(concat): Json<ConcatSequences>,
+) -> Response {
+    let (mut seqs, path) = {
+        let state = state.lock();
+
+        let mut seqs = Vec::new();
+        for name in concat.sequences.iter() {
+            match StoragePath::new(&state.cfg, name, StorageType::Sequences)
+                .and_then(|p| storage::read_sequence_meta(&p))
+            {
+                Ok(Some(seq)) => seqs.push(seq),
+                Ok(None) => return APIError::NotFound(name.clone()).into_response(),
+                Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+            }
+        }
+
+        if let Err(e) = fseq::writer::check_joinable(&seqs) {
+            return APIError::BadRequest(e.to_string()).into_response();
+        }
+
+        match utils::new_sequence_path(&state, &concat.name) {
+            Ok(p) => (seqs, p),
+            Err(e) => return e.into_response(),
+        }
+    };
+
+    create_sequence(state, path, move |f| {
+        fseq::writer::concat(&mut seqs, BufWriter::new(f)).map(|_| ())
+    })
+    .await
+}
+
+/// Write a new sequence in the background then add it to the database
+/// as if it had been uploaded
+async fn create_sequence<F>(state: Arc<Mutex<State>>, path: StoragePath, build: F) -> Response
+where
+    F: FnOnce(&mut std::fs::File) -> anyhow::Result<()> + Send + 'static,
+{
+    match utils::write_sequence(state, path, build).await {
+        Ok(_) => APIError::Ok.into_response(),
+        Err(e) => APIError::UnexpectedError(e).into_response(),
+    }
+}
+
 /// Get a sequence's metadata
 ///
 /// Get the metadata belonging to a sequence
@@ -114,16 +505,121 @@ pub async fn get_sequence_meta(
     let mut state = state.lock();
 
     match db::get_sequence(&mut state.db_conn, filename) {
-        Ok(Some((seq, vars))) => Json(SequenceMeta {
-            name: seq.name,
-            id: seq.timestamp,
-            step_time: seq.step_time as u8,
-            num_frames: seq.frames as u32,
-            channel_count: seq.channels as u32,
-            variables: vars.into_iter().map(|v| (v.name, v.value)).collect(),
-        })
-        .into_response(),
+        Ok(Some((seq, vars))) => {
+            let warnings = match db::get_sequence_warnings(&mut state.db_conn, &seq) {
+                Ok(w) => w,
+                Err(e) => return APIError::UnexpectedError(e).into_response(),
+            };
+
+            Json(SequenceMeta {
+                name: seq.name,
+                id: seq.timestamp,
+                step_time: seq.step_time as u8,
+                num_frames: seq.frames as u32,
+                channel_count: seq.channels as u32,
+                checksum: seq.checksum,
+                warnings,
+                variables: vars.into_iter().map(|v| (v.name, v.value)).collect(),
+            })
+            .into_response()
+        }
         Ok(None) => APIError::NotFound("Sequence".into()).into_response(),
         Err(e) => APIError::UnexpectedError(e).into_response(),
     }
 }
+
+/// The most pixels, summed over every frame, that can be rendered in
+/// one request
+const MAX_RENDER_PIXELS: usize = 2_000_000;
+
+/// Render a sequence
+///
+/// Render part of a sequence onto the virtual display. Each frame
+/// holds the colour of every pixel of the display, in the same order
+/// as the pixels. At most 2,000,000 pixels, summed over every frame,
+/// can be rendered at once.
+#[utoipa::path(
+    get,
+    path = "/api/sequence/{filename}/render",
+    params(
+        ("filename" = String, Path, description = "The sequence to render"),
+        RenderQuery,
+    ),
+    responses(
+        (status = 200, description = "The rendered frames", body = RenderedSequence),
+        (status = 400, description = "The time range or filename is invalid", body = Status),
+        (status = 404, description = "The sequence wasn't found", body = Status),
+        (status = 500, description = "Something went wrong", body = Status)
+    ),
+    tag = "Sequences"
+)]
+pub async fn render_sequence(
+    extract::State(state): extract::State<Arc<Mutex<State>>>,
+    extract::Path(filename): extract::Path<String>,
+    extract::Query(query): extract::Query<RenderQuery>,
+) -> Response {
+    let (mut seq, display) = {
+        let state = state.lock();
+
+        let path = match StoragePath::new(&state.cfg, &filename, StorageType::Sequences) {
+            Ok(p) => p,
+            Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+        };
+
+        let seq = match storage::read_sequence_meta(&path) {
+            Ok(Some(seq)) => seq,
+            Ok(None) => return APIError::NotFound("Sequence".into()).into_response(),
+            Err(e) => return APIError::UnexpectedError(e).into_response(),
+        };
+
+        match display::read_virtual_display(&state.cfg) {
+            Ok(display) => (seq, display),
+            Err(e) => return e.into_response(),
+        }
+    };
+
+    let step_ms = seq.step_time_ms.max(1) as u64;
+    let start_frame = (query.start_ms.unwrap_or(0) / step_ms) as u32;
+    let end_frame = match query.end_ms {
+        Some(end) => ((end / step_ms) as u32).min(seq.frame_count),
+        None => seq.frame_count,
+    };
+
+    if start_frame >= end_frame {
+        return APIError::BadRequest("Invalid time range".into()).into_response();
+    }
+    if (end_frame - start_frame) as usize * display.pixels.len() > MAX_RENDER_PIXELS {
+        return APIError::BadRequest(format!(
+            "Can't render more than {MAX_RENDER_PIXELS} pixels, try a shorter time range"
+        ))
+        .into_response();
+    }
+
+    let render = tokio::task::spawn_blocking(move || {
+        let frames = (start_frame..end_frame)
+            .map(|f| {
+                let data = seq.get_frame(f)?.unwrap_or_default();
+                let rgb = display
+                    .pixels
+                    .iter()
+                    .flat_map(|p| p.color(&data))
+                    .collect::<Vec<_>>();
+                Ok(base64::engine::general_purpose::STANDARD.encode(rgb))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok::<_, anyhow::Error>(RenderedSequence {
+            step_ms: step_ms as u32,
+            start_frame,
+            pixels: display.pixels,
+            frames,
+        })
+    })
+    .await;
+
+    match render {
+        Ok(Ok(r)) => Json(r).into_response(),
+        Ok(Err(e)) => APIError::UnexpectedError(e).into_response(),
+        Err(e) => APIError::UnexpectedError(e.into()).into_response(),
+    }
+}
@@ -34,15 +34,7 @@ pub async fn get_test_pattern(
     q: Query<LengthQuery>,
     Json(seq): Json<patterns::Sequence>,
 ) -> Response {
-    let data = seq.as_vec(q.length);
-    let data = data
-        .chunks(3)
-        .map(|c| patterns::Color {
-            r: c[0],
-            g: c[1],
-            b: c[2],
-        })
-        .collect::<Vec<_>>();
+    let data = seq.colors(&patterns::Layout::line(q.length), 0);
 
     Json(data).into_response()
 }
@@ -0,0 +1,370 @@
+use std::{collections::BTreeMap, os::unix::fs::symlink, path::Path, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::{self, FromRequest},
+    http::{header, Request, StatusCode},
+    response::Response,
+    Json,
+};
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+use tokio::sync::{broadcast, mpsc, watch};
+
+use crate::{config::Config, db, fseq, models::*, state::State, storage};
+
+use super::{fpp, logs, meshes, player, sequences, upload, utils};
+
+/// Names which try to escape the storage directory, `outside` being
+/// an absolute path to a file which isn't in it
+fn escapes(outside: &Path, ext: &str) -> Vec<String> {
+    vec![
+        format!("../secret.{ext}"),
+        "..".into(),
+        outside.join(format!("secret.{ext}")).display().to_string(),
+        format!("sub/secret.{ext}"),
+        format!("link.{ext}"),
+    ]
+}
+
+/// Storage and logs in a temporary directory, with `secret` files
+/// outside them and `link` symlinks inside them pointing at the
+/// secrets
+fn setup() -> (TempDir, Arc<Mutex<State>>) {
+    let dir = tempfile::tempdir().unwrap();
+    let storage_dir = dir.path().join("storage");
+    let log_dir = dir.path().join("logs");
+
+    let cfg: Config = toml::from_str(&format!(
+        "database_url = \":memory:\"\nstorage = {:?}\n[log]\ndirectory = {:?}",
+        storage_dir, log_dir
+    ))
+    .unwrap();
+    storage::init(&cfg).unwrap();
+    std::fs::create_dir(&log_dir).unwrap();
+
+    for (sub, ext) in [
+        ("storage/sequences", "fseq"),
+        ("storage/meshes", "glb"),
+        ("logs", "log"),
+    ] {
+        let secret = dir.path().join(format!("secret.{ext}"));
+        std::fs::write(&secret, "secret").unwrap();
+        symlink(&secret, dir.path().join(sub).join(format!("link.{ext}"))).unwrap();
+    }
+
+    let mut db_conn = db::get(&cfg).unwrap();
+    db::run_migrations(&mut db_conn).unwrap();
+
+    let state = State {
+        cfg,
+        db_conn,
+        player_ctrl: mpsc::channel(1).0,
+        player: watch::channel(PlayerInfo::default()).0,
+        frames: broadcast::channel(1).0,
+        brightness: watch::channel(100).0,
+        overrides: watch::channel(BTreeMap::new()).0,
+    };
+
+    (dir, Arc::new(Mutex::new(state)))
+}
+
+fn assert_secrets_untouched(dir: &Path) {
+    for ext in ["fseq", "glb", "log"] {
+        let secret = std::fs::read_to_string(dir.join(format!("secret.{ext}"))).unwrap();
+        assert_eq!(secret, "secret", "secret.{ext} was changed");
+    }
+}
+
+async fn multipart(filename: &str) -> extract::Multipart {
+    let body = format!(
+        "--X\r\nContent-Disposition: form-data; name=\"myfile\"; filename=\"{filename}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\nowned\r\n--X--\r\n"
+    );
+    let req = Request::builder()
+        .header(header::CONTENT_TYPE, "multipart/form-data; boundary=X")
+        .body(Body::from(body))
+        .unwrap();
+
+    extract::Multipart::from_request(req, &()).await.unwrap()
+}
+
+fn assert_bad_request(res: Response, name: &str) {
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{name} was accepted");
+}
+
+#[tokio::test]
+async fn upload_rejects_escapes() {
+    let (dir, state) = setup();
+
+    for name in escapes(dir.path(), "fseq") {
+        let res = upload::file_upload(extract::State(state.clone()), multipart(&name).await).await;
+        assert_bad_request(res, &name);
+    }
+
+    assert_secrets_untouched(dir.path());
+}
+
+#[tokio::test]
+async fn get_sequence_rejects_escapes() {
+    let (dir, state) = setup();
+
+    for name in escapes(dir.path(), "fseq") {
+        let res =
+            sequences::get_sequence(extract::State(state.clone()), extract::Path(name.clone()))
+                .await;
+        assert_bad_request(res, &name);
+    }
+}
+
+#[tokio::test]
+async fn del_sequence_rejects_escapes() {
+    let (dir, state) = setup();
+
+    for name in escapes(dir.path(), "fseq") {
+        let res =
+            sequences::del_sequence(extract::State(state.clone()), extract::Path(name.clone()))
+                .await;
+        assert_bad_request(res, &name);
+    }
+
+    assert_secrets_untouched(dir.path());
+}
+
+#[tokio::test]
+async fn rename_sequence_rejects_escapes() {
+    let (dir, state) = setup();
+
+    for name in escapes(dir.path(), "fseq") {
+        let res = sequences::rename_sequence(
+            extract::State(state.clone()),
+            extract::Path(name.clone()),
+            Json(RenameSequence {
+                name: "new.fseq".into(),
+            }),
+        )
+        .await;
+        assert_bad_request(res, &name);
+
+        let res = sequences::rename_sequence(
+            extract::State(state.clone()),
+            extract::Path("show.fseq".into()),
+            Json(RenameSequence { name: name.clone() }),
+        )
+        .await;
+        assert_bad_request(res, &name);
+    }
+
+    assert_secrets_untouched(dir.path());
+}
+
+#[tokio::test]
+async fn download_mesh_rejects_escapes() {
+    let (dir, state) = setup();
+
+    for name in escapes(dir.path(), "glb") {
+        let res =
+            meshes::download_mesh(extract::State(state.clone()), extract::Path(name.clone())).await;
+        assert_bad_request(res, &name);
+    }
+}
+
+#[tokio::test]
+async fn get_log_rejects_escapes() {
+    let (dir, state) = setup();
+
+    for name in escapes(dir.path(), "log") {
+        let res = logs::get_log(extract::State(state.clone()), extract::Path(name.clone())).await;
+        assert_bad_request(res, &name);
+    }
+}
+
+#[tokio::test]
+async fn move_file_rejects_escapes() {
+    let (dir, state) = setup();
+
+    for name in escapes(dir.path(), "fseq") {
+        let res = fpp::fpp_command(
+            extract::State(state.clone()),
+            extract::Query(CommandQuery {
+                command: "moveFile".into(),
+                file: name.clone(),
+            }),
+        )
+        .await;
+        assert_bad_request(res, &name);
+    }
+}
+
+#[tokio::test]
+async fn move_file_finds_uploads() {
+    let (_dir, state) = setup();
+
+    let res = upload::file_upload(extract::State(state.clone()), multipart("new.fseq").await).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = fpp::fpp_command(
+        extract::State(state.clone()),
+        extract::Query(CommandQuery {
+            command: "moveFile".into(),
+            file: "new.fseq".into(),
+        }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+/// Write a one frame sequence to storage and add it to the database
+fn add_sequence(dir: &Path, state: &Arc<Mutex<State>>, name: &str) {
+    let path = dir.join("storage/sequences").join(name);
+    let file = std::fs::File::create(&path).unwrap();
+    let mut w = fseq::writer::Writer::new(file, 30, 1, 50, &[], &[]).unwrap();
+    w.write_frame(&[]).unwrap();
+    w.finish().unwrap();
+    let seq = fseq::parser::parse(path.to_str().unwrap()).unwrap();
+
+    utils::register_sequence(&mut state.lock(), *seq, String::new()).unwrap();
+}
+
+#[tokio::test]
+async fn register_sequence_without_outputs_or_models() {
+    let (dir, state) = setup();
+    add_sequence(dir.path(), &state, "show.fseq");
+
+    let mut state = state.lock();
+    let (seq, _) = db::get_sequence(&mut state.db_conn, "show.fseq".into())
+        .unwrap()
+        .unwrap();
+    assert!(db::get_sequence_warnings(&mut state.db_conn, &seq)
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn copy_sequence_rejects_escapes() {
+    let (dir, state) = setup();
+    add_sequence(dir.path(), &state, "show.fseq");
+
+    for name in escapes(dir.path(), "fseq") {
+        let res = sequences::copy_sequence(
+            extract::State(state.clone()),
+            extract::Path(name.clone()),
+            Json(CopySequence {
+                name: "new.fseq".into(),
+            }),
+        )
+        .await;
+        assert_bad_request(res, &name);
+
+        let res = sequences::copy_sequence(
+            extract::State(state.clone()),
+            extract::Path("show.fseq".into()),
+            Json(CopySequence { name: name.clone() }),
+        )
+        .await;
+        assert_bad_request(res, &name);
+    }
+
+    assert_secrets_untouched(dir.path());
+}
+
+#[tokio::test]
+async fn copy_sequence_registers_the_copy() {
+    let (dir, state) = setup();
+    add_sequence(dir.path(), &state, "show.fseq");
+    db::set_sequence_tags(
+        &mut state.lock().db_conn,
+        "show.fseq".into(),
+        vec!["christmas".into()],
+    )
+    .unwrap();
+
+    let res = sequences::copy_sequence(
+        extract::State(state.clone()),
+        extract::Path("show.fseq".into()),
+        Json(CopySequence {
+            name: "copy.fseq".into(),
+        }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let original = std::fs::read(dir.path().join("storage/sequences/show.fseq")).unwrap();
+    let copy = std::fs::read(dir.path().join("storage/sequences/copy.fseq")).unwrap();
+    assert_eq!(original, copy);
+
+    {
+        let mut state = state.lock();
+        let (seq, _) = db::get_sequence(&mut state.db_conn, "copy.fseq".into())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            db::get_sequence_tags(&mut state.db_conn, &seq).unwrap(),
+            vec!["christmas"]
+        );
+        assert_eq!(seq.checksum, storage::hex(&Sha256::digest(&copy)));
+    }
+
+    // The name is taken now
+    let res = sequences::copy_sequence(
+        extract::State(state.clone()),
+        extract::Path("show.fseq".into()),
+        Json(CopySequence {
+            name: "copy.fseq".into(),
+        }),
+    )
+    .await;
+    assert_bad_request(res, "copy.fseq");
+}
+
+#[tokio::test]
+async fn recording_reads_back() {
+    let (dir, state) = setup();
+    std::fs::write(
+        dir.path().join("storage/other/outputs.json"),
+        r#"{"channelOutputs": [{"type": "universes", "startChannel": 1, "enabled": 1,
+            "timeout": 1000, "channelCount": -1, "universes": [{"description": "",
+            "active": 1, "address": "10.0.0.1", "startChannel": 1, "channelCount": 6,
+            "id": 1, "deDuplicate": 0, "priority": 0, "monitor": 1, "type": 4}]}]}"#,
+    )
+    .unwrap();
+
+    let res = player::start_recording(
+        extract::State(state.clone()),
+        Json(RecordSpec {
+            name: "rec.fseq".into(),
+            duration_ms: 50,
+            step_ms: Some(10),
+        }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Keep sending until the recording is finished
+    let (frames, info) = {
+        let state = state.lock();
+        (state.frames.clone(), state.player.subscribe())
+    };
+    while info.borrow().recording.is_some() {
+        let _ = frames.send(crate::player::Data {
+            offset: 0,
+            data: vec![1, 2, 3, 4, 5, 6],
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+    }
+    assert_eq!(info.borrow().last_error, None);
+
+    let path = dir.path().join("storage/sequences/rec.fseq");
+    let mut seq = fseq::parser::parse(path.to_str().unwrap()).unwrap();
+    assert_eq!(seq.channel_count, 6);
+    assert_eq!(seq.frame_count, 5);
+    assert_eq!(seq.step_time_ms, 10);
+    assert_eq!(seq.get_frame(4).unwrap().unwrap(), vec![1, 2, 3, 4, 5, 6]);
+
+    assert!(
+        db::get_sequence(&mut state.lock().db_conn, "rec.fseq".into())
+            .unwrap()
+            .is_some()
+    );
+}
@@ -1,13 +1,22 @@
-use std::{io::Write, path::Path, sync::Arc};
+use std::sync::Arc;
 
 use axum::{
-    extract,
+    extract::{self, multipart::Field},
     response::{IntoResponse, Response},
 };
-use axum_typed_multipart::TypedMultipart;
 use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
 
-use crate::{db, models::*, state::State, storage, web::error::APIError};
+use crate::{
+    models::*,
+    state::State,
+    storage::{self, StoragePath},
+    web::{error::APIError, utils},
+};
+
+/// Default size limit of an upload in MiB
+const DEFAULT_MAX_UPLOAD_MB: u64 = 1024;
 
 /// Upload a file
 ///
@@ -21,55 +30,67 @@ use crate::{db, models::*, state::State, storage, web::error::APIError};
     request_body(content_type = "multipart/form-data", content = FileUpload),
     responses(
         (status = 200, description = "File uploaded successfully", body = Status),
-        (status = 400, description = "Unrecognized file type", body = Status),
+        (status = 400, description = "Unrecognized file type or invalid filename", body = Status),
+        (status = 413, description = "The upload is too large", body = Status),
         (status = 500, description = "Something went wrong", body = Status)
     ),
     tag = "Upload"
 )]
 pub async fn file_upload(
     extract::State(state): extract::State<Arc<Mutex<State>>>,
-    TypedMultipart(FileUpload { myfile }): TypedMultipart<FileUpload>,
+    mut multipart: extract::Multipart,
 ) -> Response {
-    for f in myfile.into_iter() {
-        if let Some(filename) = f.metadata.file_name {
-            let dir = match storage::get_dir(&filename) {
-                Some(d) => d,
-                None => {
-                    return APIError::BadRequest("Unrecognized file type".into()).into_response();
-                }
-            };
+    let limit = state
+        .lock()
+        .cfg
+        .web
+        .as_ref()
+        .and_then(|w| w.max_upload_mb)
+        .unwrap_or(DEFAULT_MAX_UPLOAD_MB)
+        * 1024
+        * 1024;
+    let mut received = 0;
 
-            let mut state = state.lock();
-            let path = Path::new(&state.cfg.storage)
-                .join(dir.to_string())
-                .join(&filename);
-            tracing::info!("Processing upload: {:?}", path);
-
-            let mut file = match std::fs::OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(path)
-            {
-                Ok(file) => file,
-                Err(e) => {
-                    tracing::error!("Error opening file: {e}");
-                    return APIError::UnexpectedError(e.into()).into_response();
-                }
-            };
+    loop {
+        let mut field = match multipart.next_field().await {
+            Ok(Some(f)) => f,
+            Ok(None) => break,
+            Err(e) => return APIError::BadRequest(e.body_text()).into_response(),
+        };
+
+        if field.name() != Some("myfile") {
+            continue;
+        }
+        let Some(filename) = field.file_name().map(str::to_string) else {
+            continue;
+        };
 
-            if let Err(e) = file.write_all(&f.contents) {
-                tracing::error!("Error writing file: {e}");
-                return APIError::UnexpectedError(e.into()).into_response();
+        let dir = match storage::get_dir(&filename) {
+            Some(d) => d,
+            None => {
+                return APIError::BadRequest("Unrecognized file type".into()).into_response();
             }
+        };
+
+        let path = match StoragePath::new(&state.lock().cfg, &filename, dir) {
+            Ok(p) => p,
+            Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+        };
+        tracing::info!("Processing upload: {:?}", path.path());
+
+        let checksum = match save_field(&mut field, &path, limit, &mut received).await {
+            Ok(c) => c,
+            Err(e) => return e.into_response(),
+        };
+        tracing::info!("Uploaded {:?} with SHA-256 {checksum}", path.path());
 
-            // Check sequences are valid and add to database
-            if let storage::StorageType::Sequences = dir {
-                if let Ok(Some(meta)) = storage::read_sequence_meta(&state.cfg, &filename) {
-                    if let Err(e) = db::new_sequence(&mut state.db_conn, meta) {
-                        tracing::error!("Error adding sequence to database: {e}");
-                        return APIError::UnexpectedError(e).into_response();
-                    }
+        // Check sequences are valid and add to database
+        if let storage::StorageType::Sequences = dir {
+            let mut state = state.lock();
+            if let Ok(Some(meta)) = storage::read_sequence_meta(&path) {
+                if let Err(e) = utils::register_sequence(&mut state, meta, checksum) {
+                    tracing::error!("Error adding sequence to database: {e}");
+                    return APIError::UnexpectedError(e).into_response();
                 }
             }
         }
@@ -77,3 +98,58 @@ pub async fn file_upload(
 
     APIError::Ok.into_response()
 }
+
+/// Stream a file to a temporary file next to its destination, only
+/// replacing the destination once the whole file has arrived
+///
+/// Returns the SHA-256 of the file
+async fn save_field(
+    field: &mut Field<'_>,
+    path: &StoragePath,
+    limit: u64,
+    received: &mut u64,
+) -> Result<String, APIError> {
+    let dir = path.path().parent().unwrap_or(path.path());
+    let tmp = tempfile::Builder::new()
+        .prefix(".upload-")
+        .tempfile_in(dir)
+        .map_err(|e| APIError::UnexpectedError(e.into()))?;
+
+    let mut file = tokio::fs::File::from_std(
+        tmp.reopen()
+            .map_err(|e| APIError::UnexpectedError(e.into()))?,
+    );
+    let mut hasher = Sha256::new();
+
+    loop {
+        let chunk = match field.chunk().await {
+            Ok(Some(c)) => c,
+            Ok(None) => break,
+            Err(e) => return Err(APIError::BadRequest(e.body_text())),
+        };
+
+        *received += chunk.len() as u64;
+        if *received > limit {
+            return Err(APIError::PayloadTooLarge(format!(
+                "Uploads are limited to {} MiB",
+                limit / 1024 / 1024
+            )));
+        }
+
+        hasher.update(&chunk);
+        if let Err(e) = file.write_all(&chunk).await {
+            tracing::error!("Error writing file: {e}");
+            return Err(APIError::UnexpectedError(e.into()));
+        }
+    }
+
+    if let Err(e) = file.sync_all().await {
+        return Err(APIError::UnexpectedError(e.into()));
+    }
+    drop(file);
+
+    tmp.persist(path)
+        .map_err(|e| APIError::UnexpectedError(e.error.into()))?;
+
+    Ok(storage::hex(&hasher.finalize()))
+}
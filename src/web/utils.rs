@@ -7,15 +7,89 @@ use axum::{
 use parking_lot::Mutex;
 
 use crate::{
+    db, fseq,
     state::State,
-    storage::{self, StorageType},
+    storage::{self, StoragePath, StorageType},
+    validate,
     web::error::APIError,
 };
 
+/// Add a sequence to the database along with any problems found
+/// comparing it with the outputs and models
+pub fn register_sequence(
+    state: &mut State,
+    seq: fseq::parser::FSeq,
+    checksum: String,
+) -> anyhow::Result<()> {
+    let outputs = storage::read_outputs(&state.cfg).ok();
+    let models = storage::read_models(&state.cfg).ok();
+
+    let warnings = validate::sequence_warnings(&seq, outputs.as_ref(), models.as_deref());
+    for w in warnings.iter() {
+        tracing::warn!("{}: {w}", seq.filename);
+    }
+
+    db::new_sequence(&mut state.db_conn, seq, checksum, warnings)
+}
+
+/// Check a new sequence can be created with the name
+pub fn new_sequence_path(state: &State, name: &str) -> Result<StoragePath, APIError> {
+    if !matches!(
+        storage::get_dir(&name.to_string()),
+        Some(StorageType::Sequences)
+    ) {
+        return Err(APIError::BadRequest(
+            "Sequences must have the .fseq extension".into(),
+        ));
+    }
+
+    let path = StoragePath::new(&state.cfg, name, StorageType::Sequences)
+        .map_err(|e| APIError::BadRequest(e.to_string()))?;
+    if path.path().exists() {
+        return Err(APIError::BadRequest(format!("{name} already exists")));
+    }
+
+    Ok(path)
+}
+
+/// Write a new sequence in the background then add it to the database
+///
+/// The sequence is written to a temporary file first so nothing is
+/// left behind if it fails.
+pub async fn write_sequence<F>(
+    state: Arc<Mutex<State>>,
+    path: StoragePath,
+    build: F,
+) -> anyhow::Result<()>
+where
+    F: FnOnce(&mut std::fs::File) -> anyhow::Result<()> + Send + 'static,
+{
+    let (seq, checksum) = tokio::task::spawn_blocking(move || {
+        let dir = path.path().parent().unwrap_or(path.path());
+        let mut tmp = tempfile::Builder::new().prefix(".new-").tempfile_in(dir)?;
+        build(tmp.as_file_mut())?;
+        tmp.persist_noclobber(&path)?;
+
+        let checksum = storage::checksum(&path)?;
+        let seq = storage::read_sequence_meta(&path)?
+            .ok_or_else(|| anyhow::anyhow!("{} is not a valid sequence", path.filename()))?;
+
+        Ok::<_, anyhow::Error>((seq, checksum))
+    })
+    .await??;
+
+    register_sequence(&mut state.lock(), seq, checksum)
+}
+
 pub async fn upload_other(state: Arc<Mutex<State>>, filename: String, data: Vec<u8>) -> Response {
     let state = state.lock();
 
-    match storage::upload_file(&state.cfg, &filename, StorageType::Other, data) {
+    let path = match StoragePath::new(&state.cfg, &filename, StorageType::Other) {
+        Ok(p) => p,
+        Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+    };
+
+    match storage::upload_file(&path, data) {
         Ok(_) => APIError::Ok.into_response(),
         Err(e) => APIError::UnexpectedError(e).into_response(),
     }
@@ -28,7 +102,12 @@ pub async fn download_other(
 ) -> Response {
     let state = state.lock();
 
-    match storage::read_file(&state.cfg, &filename, StorageType::Other) {
+    let path = match StoragePath::new(&state.cfg, &filename, StorageType::Other) {
+        Ok(p) => p,
+        Err(e) => return APIError::BadRequest(e.to_string()).into_response(),
+    };
+
+    match storage::read_file(&path) {
         Ok(Some(d)) => ([(header::CONTENT_TYPE, mimetype)], d).into_response(),
         Ok(None) => APIError::NotFound(filename).into_response(),
         Err(e) => APIError::UnexpectedError(e).into_response(),
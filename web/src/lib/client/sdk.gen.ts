@@ -1,11 +1,26 @@
 import type { Client, Options as Options2, TDataShape } from "./client";
 import type {
+  ConcatSequencesData,
+  ConcatSequencesErrors,
+  ConcatSequencesResponses,
+  CopySequenceData,
+  CopySequenceErrors,
+  CopySequenceResponses,
+  CurrentUserData,
+  CurrentUserErrors,
+  CurrentUserResponses,
   DelButtonData,
   DelButtonErrors,
   DelButtonResponses,
   DelMeshData,
   DelMeshErrors,
   DelMeshResponses,
+  DelOverrideData,
+  DelOverrideErrors,
+  DelOverrideResponses,
+  DelOverridesData,
+  DelOverridesErrors,
+  DelOverridesResponses,
   DelPlaylistData,
   DelPlaylistErrors,
   DelPlaylistResponses,
@@ -27,6 +42,9 @@ import type {
   FppCommandData,
   FppCommandErrors,
   FppCommandResponses,
+  GetBrightnessData,
+  GetBrightnessErrors,
+  GetBrightnessResponses,
   GetButtonData,
   GetButtonErrors,
   GetButtonResponses,
@@ -39,6 +57,9 @@ import type {
   GetLogData,
   GetLogErrors,
   GetLogResponses,
+  GetOutputSettingsData,
+  GetOutputSettingsErrors,
+  GetOutputSettingsResponses,
   GetOutputsData,
   GetOutputsErrors,
   GetOutputsResponses,
@@ -63,6 +84,9 @@ import type {
   GetTestPatternData,
   GetTestPatternErrors,
   GetTestPatternResponses,
+  GetVirtualDisplayData,
+  GetVirtualDisplayErrors,
+  GetVirtualDisplayResponses,
   ListButtonsData,
   ListButtonsErrors,
   ListButtonsResponses,
@@ -75,6 +99,9 @@ import type {
   ListModelsData,
   ListModelsErrors,
   ListModelsResponses,
+  ListOverridesData,
+  ListOverridesErrors,
+  ListOverridesResponses,
   ListPlaylistsData,
   ListPlaylistsErrors,
   ListPlaylistsNumberedData,
@@ -90,9 +117,18 @@ import type {
   ListSequencesData,
   ListSequencesErrors,
   ListSequencesResponses,
+  ListTagsData,
+  ListTagsErrors,
+  ListTagsResponses,
   ListTimezonesData,
   ListTimezonesErrors,
   ListTimezonesResponses,
+  LoginData,
+  LoginErrors,
+  LoginResponses,
+  LogoutData,
+  LogoutErrors,
+  LogoutResponses,
   NewButtonData,
   NewButtonErrors,
   NewButtonResponses,
@@ -108,12 +144,36 @@ import type {
   NewScheduleData,
   NewScheduleErrors,
   NewScheduleResponses,
+  RenameSequenceData,
+  RenameSequenceErrors,
+  RenameSequenceResponses,
   RunTestData,
   RunTestErrors,
   RunTestResponses,
+  SearchSequencesData,
+  SearchSequencesErrors,
+  SearchSequencesResponses,
+  SetBrightnessData,
+  SetBrightnessErrors,
+  SetBrightnessResponses,
+  SetOverrideData,
+  SetOverrideErrors,
+  SetOverrideResponses,
+  SetSequenceBrightnessData,
+  SetSequenceBrightnessErrors,
+  SetSequenceBrightnessResponses,
+  SetSequenceTagsData,
+  SetSequenceTagsErrors,
+  SetSequenceTagsResponses,
   SetTimezoneData,
   SetTimezoneErrors,
   SetTimezoneResponses,
+  StartBridgeData,
+  StartBridgeErrors,
+  StartBridgeResponses,
+  StartRecordingData,
+  StartRecordingErrors,
+  StartRecordingResponses,
   StartSchedulerData,
   StartSchedulerErrors,
   StartSchedulerResponses,
@@ -122,12 +182,18 @@ import type {
   StopResponses,
   SystemInfoData,
   SystemInfoResponses,
+  TrimSequenceData,
+  TrimSequenceErrors,
+  TrimSequenceResponses,
   UpdateButtonData,
   UpdateButtonErrors,
   UpdateButtonResponses,
   UpdateMeshData,
   UpdateMeshErrors,
   UpdateMeshResponses,
+  UpdateOutputSettingsData,
+  UpdateOutputSettingsErrors,
+  UpdateOutputSettingsResponses,
   UpdatePlaylistData,
   UpdatePlaylistErrors,
   UpdatePlaylistResponses,
@@ -273,6 +339,43 @@ export const uploadOutputs = <ThrowOnError extends boolean = false>(
     },
   });
 
+/**
+ * Get the output settings
+ *
+ * Get the colour order and gamma correction of each universe.
+ * Universes without settings are sent RGB with no correction.
+ */
+export const getOutputSettings = <ThrowOnError extends boolean = false>(
+  options?: Options<GetOutputSettingsData, ThrowOnError>,
+) =>
+  (options?.client ?? client).get<
+    GetOutputSettingsResponses,
+    GetOutputSettingsErrors,
+    ThrowOnError
+  >({ url: "/api/outputs/settings", ...options });
+
+/**
+ * Update the output settings
+ *
+ * Replace the colour order and gamma correction of every universe.
+ * Changes are used the next time the player starts.
+ */
+export const updateOutputSettings = <ThrowOnError extends boolean = false>(
+  options: Options<UpdateOutputSettingsData, ThrowOnError>,
+) =>
+  (options.client ?? client).put<
+    UpdateOutputSettingsResponses,
+    UpdateOutputSettingsErrors,
+    ThrowOnError
+  >({
+    url: "/api/outputs/settings",
+    ...options,
+    headers: {
+      "Content-Type": "application/json",
+      ...options.headers,
+    },
+  });
+
 /**
  * Get current server time and timezone
  *
@@ -348,6 +451,24 @@ export const uploadDisplay = <ThrowOnError extends boolean = false>(
     },
   });
 
+/**
+ * Get the virtual display
+ *
+ * Get the parsed VirtualDisplayMap, with the position and channels
+ * of every pixel and the bounding box of every model
+ */
+export const getVirtualDisplay = <ThrowOnError extends boolean = false>(
+  options?: Options<GetVirtualDisplayData, ThrowOnError>,
+) =>
+  (options?.client ?? client).get<
+    GetVirtualDisplayResponses,
+    GetVirtualDisplayErrors,
+    ThrowOnError
+  >({
+    url: "/api/display",
+    ...options,
+  });
+
 /**
  * Get a specific log
  */
@@ -473,6 +594,61 @@ export const uploadModels = <ThrowOnError extends boolean = false>(
     },
   });
 
+/**
+ * Override a model
+ *
+ * Force a model to a colour, off, or a brightness on top of whatever
+ * is playing. The override applies straight away and is kept until
+ * it is cleared.
+ */
+export const setOverride = <ThrowOnError extends boolean = false>(
+  options: Options<SetOverrideData, ThrowOnError>,
+) =>
+  (options.client ?? client).put<SetOverrideResponses, SetOverrideErrors, ThrowOnError>({
+    url: "/api/override/{model}",
+    ...options,
+    headers: {
+      "Content-Type": "application/json",
+      ...options.headers,
+    },
+  });
+
+/**
+ * Clear a model's override
+ */
+export const delOverride = <ThrowOnError extends boolean = false>(
+  options: Options<DelOverrideData, ThrowOnError>,
+) =>
+  (options.client ?? client).delete<DelOverrideResponses, DelOverrideErrors, ThrowOnError>({
+    url: "/api/override/{model}",
+    ...options,
+  });
+
+/**
+ * Get the model overrides
+ *
+ * Get the override of each model which has one, keyed by the name
+ * of the model.
+ */
+export const listOverrides = <ThrowOnError extends boolean = false>(
+  options?: Options<ListOverridesData, ThrowOnError>,
+) =>
+  (options?.client ?? client).get<ListOverridesResponses, ListOverridesErrors, ThrowOnError>({
+    url: "/api/overrides",
+    ...options,
+  });
+
+/**
+ * Clear every model's override
+ */
+export const delOverrides = <ThrowOnError extends boolean = false>(
+  options?: Options<DelOverridesData, ThrowOnError>,
+) =>
+  (options?.client ?? client).delete<DelOverridesResponses, DelOverridesErrors, ThrowOnError>({
+    url: "/api/overrides",
+    ...options,
+  });
+
 /**
  * Get the player status
  */
@@ -484,6 +660,70 @@ export const getStatus = <ThrowOnError extends boolean = false>(
     ...options,
   });
 
+/**
+ * Bridge DDP and E1.31 to the controllers
+ *
+ * Forward channel data received from xLights, or anything else
+ * sending DDP or E1.31, to the controllers until the player is
+ * stopped.
+ */
+export const startBridge = <ThrowOnError extends boolean = false>(
+  options?: Options<StartBridgeData, ThrowOnError>,
+) =>
+  (options?.client ?? client).get<StartBridgeResponses, StartBridgeErrors, ThrowOnError>({
+    url: "/api/player/bridge",
+    ...options,
+  });
+
+/**
+ * Get the master brightness
+ */
+export const getBrightness = <ThrowOnError extends boolean = false>(
+  options?: Options<GetBrightnessData, ThrowOnError>,
+) =>
+  (options?.client ?? client).get<GetBrightnessResponses, GetBrightnessErrors, ThrowOnError>({
+    url: "/api/player/brightness",
+    ...options,
+  });
+
+/**
+ * Set the master brightness
+ *
+ * Dim everything the player outputs. The change applies to whatever
+ * is playing straight away and is kept across restarts.
+ */
+export const setBrightness = <ThrowOnError extends boolean = false>(
+  options: Options<SetBrightnessData, ThrowOnError>,
+) =>
+  (options.client ?? client).put<SetBrightnessResponses, SetBrightnessErrors, ThrowOnError>({
+    url: "/api/player/brightness",
+    ...options,
+    headers: {
+      "Content-Type": "application/json",
+      ...options.headers,
+    },
+  });
+
+/**
+ * Record the player's output
+ *
+ * Save everything sent to the controllers for a length of time as a
+ * new sequence, whether it comes from a playlist, a test or the
+ * bridge. The recording carries on in the background, the player
+ * status shows the sequence being recorded until it is finished.
+ */
+export const startRecording = <ThrowOnError extends boolean = false>(
+  options: Options<StartRecordingData, ThrowOnError>,
+) =>
+  (options.client ?? client).post<StartRecordingResponses, StartRecordingErrors, ThrowOnError>({
+    url: "/api/player/record",
+    ...options,
+    headers: {
+      "Content-Type": "application/json",
+      ...options.headers,
+    },
+  });
+
 /**
  * Start the player scheduling
  */
@@ -497,6 +737,9 @@ export const startScheduler = <ThrowOnError extends boolean = false>(
 
 /**
  * Stop the player
+ *
+ * Stopping a test goes back to the scheduler if it was running before
+ * the test, stop again to stop the scheduler too.
  */
 export const stop = <ThrowOnError extends boolean = false>(
   options?: Options<StopData, ThrowOnError>,
@@ -508,6 +751,10 @@ export const stop = <ThrowOnError extends boolean = false>(
 
 /**
  * Run LED test patterns
+ *
+ * Tests with a duration stop by themselves. Once a test stops, by
+ * itself or when the player is stopped, the scheduler is started again
+ * if it was running before.
  */
 export const runTest = <ThrowOnError extends boolean = false>(
   options: Options<RunTestData, ThrowOnError>,
@@ -805,6 +1052,154 @@ export const listSequences = <ThrowOnError extends boolean = false>(
     ...options,
   });
 
+/**
+ * Search sequences
+ *
+ * Find sequences by name, FSEQ variables, or tag
+ */
+export const searchSequences = <ThrowOnError extends boolean = false>(
+  options?: Options<SearchSequencesData, ThrowOnError>,
+) =>
+  (options?.client ?? client).get<
+    SearchSequencesResponses,
+    SearchSequencesErrors,
+    ThrowOnError
+  >({
+    url: "/api/sequences/search",
+    ...options,
+  });
+
+/**
+ * List tags
+ *
+ * List every tag given to a sequence
+ */
+export const listTags = <ThrowOnError extends boolean = false>(
+  options?: Options<ListTagsData, ThrowOnError>,
+) =>
+  (options?.client ?? client).get<ListTagsResponses, ListTagsErrors, ThrowOnError>({
+    url: "/api/sequences/tags",
+    ...options,
+  });
+
+/**
+ * Join sequences
+ *
+ * Create a new sequence by playing sequences one after another. The
+ * sequences must have the same channels and step time.
+ */
+export const concatSequences = <ThrowOnError extends boolean = false>(
+  options: Options<ConcatSequencesData, ThrowOnError>,
+) =>
+  (options.client ?? client).post<
+    ConcatSequencesResponses,
+    ConcatSequencesErrors,
+    ThrowOnError
+  >({
+    url: "/api/sequences/concat",
+    ...options,
+    headers: {
+      "Content-Type": "application/json",
+      ...options.headers,
+    },
+  });
+
+/**
+ * Set a sequence's brightness
+ *
+ * Dim a sequence without editing it. This is combined with the
+ * master brightness and that of the playlist entry.
+ */
+export const setSequenceBrightness = <ThrowOnError extends boolean = false>(
+  options: Options<SetSequenceBrightnessData, ThrowOnError>,
+) =>
+  (options.client ?? client).put<
+    SetSequenceBrightnessResponses,
+    SetSequenceBrightnessErrors,
+    ThrowOnError
+  >({
+    url: "/api/sequence/{filename}/brightness",
+    ...options,
+    headers: {
+      "Content-Type": "application/json",
+      ...options.headers,
+    },
+  });
+
+/**
+ * Copy a sequence
+ *
+ * Copy a sequence file to a new name. The copy is added as if it had
+ * been uploaded and has the same tags.
+ */
+export const copySequence = <ThrowOnError extends boolean = false>(
+  options: Options<CopySequenceData, ThrowOnError>,
+) =>
+  (options.client ?? client).post<CopySequenceResponses, CopySequenceErrors, ThrowOnError>({
+    url: "/api/sequence/{filename}/copy",
+    ...options,
+    headers: {
+      "Content-Type": "application/json",
+      ...options.headers,
+    },
+  });
+
+/**
+ * Rename a sequence
+ *
+ * Rename a sequence file. Playlists and buttons using the sequence
+ * are updated to use the new name.
+ */
+export const renameSequence = <ThrowOnError extends boolean = false>(
+  options: Options<RenameSequenceData, ThrowOnError>,
+) =>
+  (options.client ?? client).post<RenameSequenceResponses, RenameSequenceErrors, ThrowOnError>({
+    url: "/api/sequence/{filename}/rename",
+    ...options,
+    headers: {
+      "Content-Type": "application/json",
+      ...options.headers,
+    },
+  });
+
+/**
+ * Tag a sequence
+ *
+ * Replace the tags of a sequence
+ */
+export const setSequenceTags = <ThrowOnError extends boolean = false>(
+  options: Options<SetSequenceTagsData, ThrowOnError>,
+) =>
+  (options.client ?? client).put<
+    SetSequenceTagsResponses,
+    SetSequenceTagsErrors,
+    ThrowOnError
+  >({
+    url: "/api/sequence/{filename}/tags",
+    ...options,
+    headers: {
+      "Content-Type": "application/json",
+      ...options.headers,
+    },
+  });
+
+/**
+ * Trim a sequence
+ *
+ * Create a new sequence from part of an existing one
+ */
+export const trimSequence = <ThrowOnError extends boolean = false>(
+  options: Options<TrimSequenceData, ThrowOnError>,
+) =>
+  (options.client ?? client).post<TrimSequenceResponses, TrimSequenceErrors, ThrowOnError>({
+    url: "/api/sequence/{filename}/trim",
+    ...options,
+    headers: {
+      "Content-Type": "application/json",
+      ...options.headers,
+    },
+  });
+
 /**
  * Get system info.
  *
@@ -871,3 +1266,44 @@ export const fppCommand = <ThrowOnError extends boolean = false>(
     url: "/fppxml.php",
     ...options,
   });
+
+/**
+ * Log in
+ *
+ * Start a session and set the session cookie
+ */
+export const login = <ThrowOnError extends boolean = false>(
+  options: Options<LoginData, ThrowOnError>,
+) =>
+  (options.client ?? client).post<LoginResponses, LoginErrors, ThrowOnError>({
+    url: "/api/auth/login",
+    ...options,
+    headers: {
+      "Content-Type": "application/json",
+      ...options.headers,
+    },
+  });
+
+/**
+ * Log out
+ *
+ * End the current session
+ */
+export const logout = <ThrowOnError extends boolean = false>(
+  options?: Options<LogoutData, ThrowOnError>,
+) =>
+  (options?.client ?? client).post<LogoutResponses, LogoutErrors, ThrowOnError>({
+    url: "/api/auth/logout",
+    ...options,
+  });
+
+/**
+ * Get the current user
+ */
+export const currentUser = <ThrowOnError extends boolean = false>(
+  options?: Options<CurrentUserData, ThrowOnError>,
+) =>
+  (options?.client ?? client).get<CurrentUserResponses, CurrentUserErrors, ThrowOnError>({
+    url: "/api/auth/me",
+    ...options,
+  });
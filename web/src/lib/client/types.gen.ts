@@ -16,6 +16,18 @@ export type Action = (typeof Action)[keyof typeof Action];
 
 export type BinaryFile = Blob | File;
 
+export type BoundingBox = {
+  max: Point;
+  min: Point;
+};
+
+export type Brightness = {
+  /**
+   * Percentage brightness, scaled so 50% looks half as bright
+   */
+  brightness: number;
+};
+
 export type Button = {
   action: Action;
   action_target: string;
@@ -52,11 +64,80 @@ export type Color = {
   r: number;
 };
 
+export const ColorOrder = {
+  RGB: "RGB",
+  RBG: "RBG",
+  GRB: "GRB",
+  GBR: "GBR",
+  BRG: "BRG",
+  BGR: "BGR",
+  RGBW: "RGBW",
+  GRBW: "GRBW",
+} as const;
+
+export type ColorOrder = (typeof ColorOrder)[keyof typeof ColorOrder];
+
+export type ConcatSequences = {
+  /**
+   * Filename of the new sequence
+   */
+  name: string;
+  /**
+   * The sequences to join, in order
+   */
+  sequences: Array<string>;
+};
+
+export type CopySequence = {
+  /**
+   * Filename of the copy
+   */
+  name: string;
+};
+
+export type Credentials = {
+  password: string;
+  username: string;
+};
+
+export type CurrentUser = {
+  /**
+   * Whether logging in is required
+   */
+  auth_enabled: boolean;
+  role: Role;
+  /**
+   * The user or API token making the request
+   */
+  username: string;
+};
+
 export type DiskUtilization = {
   Media: FreeTotal;
   Root: FreeTotal;
 };
 
+export type DisplayModel = {
+  bounds: BoundingBox;
+  /**
+   * Number of channels used by the model
+   */
+  channel_count: number;
+  /**
+   * Index of the first pixel of the model
+   */
+  first_pixel: number;
+  name: string;
+  /**
+   * Number of pixels in the model
+   */
+  pixel_count: number;
+  /**
+   * Zero based offset of the first channel of the model
+   */
+  start_channel: number;
+};
+
 export type FileUpload = {
   /**
    * File or files to upload
@@ -138,6 +219,14 @@ export type NewScene = {
   name: string;
 };
 
+export type Node = {
+  color: Color;
+  /**
+   * Number of the node, counting from 1 as xLights does
+   */
+  number: number;
+};
+
 export type NumberedPlaylist = {
   id: number;
   name: string;
@@ -166,6 +255,105 @@ export const Pattern = {
 
 export type Pattern = (typeof Pattern)[keyof typeof Pattern];
 
+export type OutputSettings = {
+  color_order?: ColorOrder;
+  /**
+   * Gamma correction to apply, none sends values unchanged
+   */
+  gamma?: number | null;
+  /**
+   * ID of the universe the settings apply to
+   */
+  id: number;
+};
+
+/**
+ * Manual control of a model on top of whatever is playing
+ */
+export type Override =
+  | {
+      solid: Color;
+    }
+  | "off"
+  | {
+      brightness: number;
+    };
+
+export type Pixel = {
+  /**
+   * Zero based offset of the first channel of the pixel
+   */
+  channel: number;
+  /**
+   * Number of channels making up the pixel
+   */
+  channels: number;
+  /**
+   * Order of the colours in the channels
+   */
+  color_order: string;
+  x: number;
+  y: number;
+  z: number;
+};
+
+export type PlayerInfo = {
+  /**
+   * Milliseconds into the sequence
+   */
+  elapsed_ms: number;
+  /**
+   * The current frame of the sequence
+   */
+  frame: number;
+  /**
+   * The number of frames in the sequence
+   */
+  frames: number;
+  /**
+   * The most recent error reported by the player
+   */
+  last_error?: string | null;
+  /**
+   * The current loop through the playlist
+   */
+  loop_count: number;
+  next_schedule?: null | UpcomingSchedule;
+  /**
+   * The playlist currently playing
+   */
+  playlist?: string | null;
+  /**
+   * The number of sequences in the playlist
+   */
+  playlist_count: number;
+  /**
+   * Index of the current sequence within the playlist
+   */
+  playlist_index: number;
+  /**
+   * The sequence the output is being recorded to
+   */
+  recording?: string | null;
+  /**
+   * Milliseconds remaining of the sequence
+   */
+  remaining_ms: number;
+  /**
+   * The schedule currently running
+   */
+  schedule?: string | null;
+  /**
+   * The health of each controller being sent to
+   */
+  senders: Array<SenderStatus>;
+  /**
+   * The sequence currently playing
+   */
+  sequence?: string | null;
+  status: PlayerStatus;
+};
+
 export type PlayerState =
   | "schedule"
   | {
@@ -177,6 +365,7 @@ export type PlayerState =
   | {
       test: TestSpec;
     }
+  | "bridge"
   | "stop";
 
 export const PlayerStatus = {
@@ -184,11 +373,18 @@ export const PlayerStatus = {
   PLAYLIST: "playlist",
   SEQUENCE: "sequence",
   TESTING: "testing",
+  BRIDGE: "bridge",
   STOPPED: "stopped",
 } as const;
 
 export type PlayerStatus = (typeof PlayerStatus)[keyof typeof PlayerStatus];
 
+export type Point = {
+  x: number;
+  y: number;
+  z: number;
+};
+
 export type Playlist = {
   desc: string;
   empty: boolean;
@@ -204,6 +400,10 @@ export type Playlist = {
 };
 
 export type PlaylistEntry = {
+  /**
+   * Percentage brightness to play the sequence at
+   */
+  brightness?: number | null;
   duration?: number | null;
   enabled: boolean;
   playOnce: boolean;
@@ -216,6 +416,36 @@ export type PlaylistInfo = {
   total_items: number;
 };
 
+export const Role = {
+  VIEWER: "viewer",
+  OPERATOR: "operator",
+  ADMIN: "admin",
+} as const;
+
+export type Role = (typeof Role)[keyof typeof Role];
+
+export type RecordSpec = {
+  /**
+   * How long to record for
+   */
+  duration_ms: number;
+  /**
+   * Name of the sequence to record to
+   */
+  name: string;
+  /**
+   * Time between frames, defaults to 50ms
+   */
+  step_ms?: number | null;
+};
+
+export type RenameSequence = {
+  /**
+   * The new filename
+   */
+  name: string;
+};
+
 export type Scene = {
   cam_pos_x: number;
   cam_pos_y: number;
@@ -266,13 +496,56 @@ export type Sequence =
     }
   | {
       custom_moving_pattern: Array<Color>;
-    };
+    }
+  | {
+      horizontal_sweep: Color;
+    }
+  | {
+      vertical_sweep: Color;
+    }
+  | {
+      strings: Pattern;
+    }
+  | "corners"
+  | {
+      node: Node;
+    }
+  | "binary_nodes";
+
+export type SequenceDetails = {
+  /**
+   * Percentage brightness the sequence is played at
+   */
+  brightness: number;
+  /**
+   * Number of channels
+   */
+  channels: number;
+  /**
+   * Length of the sequence in milliseconds
+   */
+  duration_ms: number;
+  /**
+   * Number of frames
+   */
+  frames: number;
+  name: string;
+  tags: Array<string>;
+  /**
+   * Unix timestamp of when the sequence was uploaded
+   */
+  uploaded: number;
+};
 
 export type SequenceMeta = {
   /**
    * Number of channels
    */
   ChannelCount: number;
+  /**
+   * SHA-256 of the file when it was uploaded
+   */
+  Checksum: string;
   /**
    * ID of the sequence (Likely the creation timestamp)
    */
@@ -289,6 +562,10 @@ export type SequenceMeta = {
    * Step time in milliseconds
    */
   StepTime: number;
+  /**
+   * Problems found comparing the sequence with the outputs and models
+   */
+  Warnings: Array<string>;
   /**
    * Any additional variables
    */
@@ -297,6 +574,33 @@ export type SequenceMeta = {
   };
 };
 
+export const SequenceSort = {
+  NAME: "name",
+  DURATION: "duration",
+  UPLOADED: "uploaded",
+} as const;
+
+export type SequenceSort = (typeof SequenceSort)[keyof typeof SequenceSort];
+
+export type SenderStatus = {
+  /**
+   * The address of the controller
+   */
+  address: string;
+  /**
+   * Whether the last frame was sent successfully
+   */
+  healthy: boolean;
+  /**
+   * How many times connecting or sending to the controller failed
+   */
+  errors: number;
+  /**
+   * The most recent error
+   */
+  last_error?: string | null;
+};
+
 export type Status = {
   /**
    * What went wrong
@@ -403,6 +707,11 @@ export type SystemUtilization = {
 };
 
 export type TestSpec = {
+  /**
+   * Stop testing after this many milliseconds, otherwise test until
+   * stopped. Either way the scheduler starts again if it was running
+   */
+  duration_ms?: number | null;
   step_ms: number;
   tests: {
     [key: string]: Sequence;
@@ -420,6 +729,36 @@ export type TimeAndTimezone = {
   timezone: string;
 };
 
+export type TrimSequence = {
+  /**
+   * Time into the sequence to stop at
+   */
+  end_ms?: number | null;
+  /**
+   * Filename of the new sequence
+   */
+  name: string;
+  /**
+   * Time into the sequence to start from
+   */
+  start_ms?: number | null;
+};
+
+export type UpcomingSchedule = {
+  /**
+   * The playlist the schedule will play
+   */
+  playlist: string;
+  /**
+   * The name of the schedule
+   */
+  schedule: string;
+  /**
+   * When the schedule starts
+   */
+  start: string;
+};
+
 export type Universe = {
   active: boolean;
   address: string;
@@ -433,6 +772,25 @@ export type Universe = {
   type: number;
 };
 
+export type VirtualDisplay = {
+  /**
+   * Height of the xLights preview
+   */
+  height: number;
+  /**
+   * The models making up the display
+   */
+  models: Array<DisplayModel>;
+  /**
+   * Every pixel in the display
+   */
+  pixels: Array<Pixel>;
+  /**
+   * Width of the xLights preview
+   */
+  width: number;
+};
+
 export type NewButtonData = {
   body: NewButton;
   path?: never;
@@ -643,6 +1001,63 @@ export type UploadOutputsResponses = {
 
 export type UploadOutputsResponse = UploadOutputsResponses[keyof UploadOutputsResponses];
 
+export type GetOutputSettingsData = {
+  body?: never;
+  path?: never;
+  query?: never;
+  url: "/api/outputs/settings";
+};
+
+export type GetOutputSettingsErrors = {
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type GetOutputSettingsError = GetOutputSettingsErrors[keyof GetOutputSettingsErrors];
+
+export type GetOutputSettingsResponses = {
+  /**
+   * The settings of each universe
+   */
+  200: Array<OutputSettings>;
+};
+
+export type GetOutputSettingsResponse =
+  GetOutputSettingsResponses[keyof GetOutputSettingsResponses];
+
+export type UpdateOutputSettingsData = {
+  body: Array<OutputSettings>;
+  path?: never;
+  query?: never;
+  url: "/api/outputs/settings";
+};
+
+export type UpdateOutputSettingsErrors = {
+  /**
+   * A gamma value is invalid
+   */
+  400: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type UpdateOutputSettingsError =
+  UpdateOutputSettingsErrors[keyof UpdateOutputSettingsErrors];
+
+export type UpdateOutputSettingsResponses = {
+  /**
+   * The settings were saved
+   */
+  200: Status;
+};
+
+export type UpdateOutputSettingsResponse =
+  UpdateOutputSettingsResponses[keyof UpdateOutputSettingsResponses];
+
 export type GetCurrentTimeAndTimezoneData = {
   body?: never;
   path?: never;
@@ -782,6 +1197,36 @@ export type UploadDisplayResponses = {
 
 export type UploadDisplayResponse = UploadDisplayResponses[keyof UploadDisplayResponses];
 
+export type GetVirtualDisplayData = {
+  body?: never;
+  path?: never;
+  query?: never;
+  url: "/api/display";
+};
+
+export type GetVirtualDisplayErrors = {
+  /**
+   * VirtualDisplayMap not found
+   */
+  404: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type GetVirtualDisplayError = GetVirtualDisplayErrors[keyof GetVirtualDisplayErrors];
+
+export type GetVirtualDisplayResponses = {
+  /**
+   * The virtual display
+   */
+  200: VirtualDisplay;
+};
+
+export type GetVirtualDisplayResponse =
+  GetVirtualDisplayResponses[keyof GetVirtualDisplayResponses];
+
 export type GetLogData = {
   body?: never;
   path: {
@@ -796,7 +1241,7 @@ export type GetLogData = {
 
 export type GetLogErrors = {
   /**
-   * Logging is disabled
+   * Logging is disabled or the name is invalid
    */
   400: Status;
   /**
@@ -917,6 +1362,10 @@ export type DownloadMeshData = {
 };
 
 export type DownloadMeshErrors = {
+  /**
+   * Invalid mesh name
+   */
+  400: Status;
   /**
    * Unknown mesh
    */
@@ -1051,11 +1500,135 @@ export type UploadModelsResponses = {
 
 export type UploadModelsResponse = UploadModelsResponses[keyof UploadModelsResponses];
 
-export type GetStatusData = {
-  body?: never;
-  path?: never;
+export type SetOverrideData = {
+  body: Override;
+  path: {
+    /**
+     * The name of the model
+     */
+    model: string;
+  };
   query?: never;
-  url: "/api/player";
+  url: "/api/override/{model}";
+};
+
+export type SetOverrideErrors = {
+  /**
+   * Brightness is more than 100%
+   */
+  400: Status;
+  /**
+   * The model wasn't found
+   */
+  404: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type SetOverrideError = SetOverrideErrors[keyof SetOverrideErrors];
+
+export type SetOverrideResponses = {
+  /**
+   * The model was overridden
+   */
+  200: Status;
+};
+
+export type SetOverrideResponse = SetOverrideResponses[keyof SetOverrideResponses];
+
+export type DelOverrideData = {
+  body?: never;
+  path: {
+    /**
+     * The name of the model
+     */
+    model: string;
+  };
+  query?: never;
+  url: "/api/override/{model}";
+};
+
+export type DelOverrideErrors = {
+  /**
+   * The model isn't overridden
+   */
+  404: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type DelOverrideError = DelOverrideErrors[keyof DelOverrideErrors];
+
+export type DelOverrideResponses = {
+  /**
+   * The override was cleared
+   */
+  200: Status;
+};
+
+export type DelOverrideResponse = DelOverrideResponses[keyof DelOverrideResponses];
+
+export type ListOverridesData = {
+  body?: never;
+  path?: never;
+  query?: never;
+  url: "/api/overrides";
+};
+
+export type ListOverridesErrors = {
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type ListOverridesError = ListOverridesErrors[keyof ListOverridesErrors];
+
+export type ListOverridesResponses = {
+  /**
+   * The override of each model
+   */
+  200: {
+    [key: string]: Override;
+  };
+};
+
+export type ListOverridesResponse = ListOverridesResponses[keyof ListOverridesResponses];
+
+export type DelOverridesData = {
+  body?: never;
+  path?: never;
+  query?: never;
+  url: "/api/overrides";
+};
+
+export type DelOverridesErrors = {
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type DelOverridesError = DelOverridesErrors[keyof DelOverridesErrors];
+
+export type DelOverridesResponses = {
+  /**
+   * The overrides were cleared
+   */
+  200: Status;
+};
+
+export type DelOverridesResponse = DelOverridesResponses[keyof DelOverridesResponses];
+
+export type GetStatusData = {
+  body?: never;
+  path?: never;
+  query?: never;
+  url: "/api/player";
 };
 
 export type GetStatusErrors = {
@@ -1069,13 +1642,121 @@ export type GetStatusError = GetStatusErrors[keyof GetStatusErrors];
 
 export type GetStatusResponses = {
   /**
-   * Status of the scheduler
+   * Status of the player
    */
-  200: PlayerStatus;
+  200: PlayerInfo;
 };
 
 export type GetStatusResponse = GetStatusResponses[keyof GetStatusResponses];
 
+export type StartBridgeData = {
+  body?: never;
+  path?: never;
+  query?: never;
+  url: "/api/player/bridge";
+};
+
+export type StartBridgeErrors = {
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type StartBridgeError = StartBridgeErrors[keyof StartBridgeErrors];
+
+export type StartBridgeResponses = {
+  /**
+   * Bridge started ok
+   */
+  200: Status;
+};
+
+export type StartBridgeResponse = StartBridgeResponses[keyof StartBridgeResponses];
+
+export type GetBrightnessData = {
+  body?: never;
+  path?: never;
+  query?: never;
+  url: "/api/player/brightness";
+};
+
+export type GetBrightnessErrors = {
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type GetBrightnessError = GetBrightnessErrors[keyof GetBrightnessErrors];
+
+export type GetBrightnessResponses = {
+  /**
+   * The master brightness
+   */
+  200: Brightness;
+};
+
+export type GetBrightnessResponse = GetBrightnessResponses[keyof GetBrightnessResponses];
+
+export type SetBrightnessData = {
+  body: Brightness;
+  path?: never;
+  query?: never;
+  url: "/api/player/brightness";
+};
+
+export type SetBrightnessErrors = {
+  /**
+   * Brightness is more than 100%
+   */
+  400: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type SetBrightnessError = SetBrightnessErrors[keyof SetBrightnessErrors];
+
+export type SetBrightnessResponses = {
+  /**
+   * Brightness set ok
+   */
+  200: Status;
+};
+
+export type SetBrightnessResponse = SetBrightnessResponses[keyof SetBrightnessResponses];
+
+export type StartRecordingData = {
+  body: RecordSpec;
+  path?: never;
+  query?: never;
+  url: "/api/player/record";
+};
+
+export type StartRecordingErrors = {
+  /**
+   * The name or length is invalid, or already recording
+   */
+  400: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type StartRecordingError = StartRecordingErrors[keyof StartRecordingErrors];
+
+export type StartRecordingResponses = {
+  /**
+   * Recording started ok
+   */
+  200: Status;
+};
+
+export type StartRecordingResponse = StartRecordingResponses[keyof StartRecordingResponses];
+
 export type StartSchedulerData = {
   body?: never;
   path?: never;
@@ -1659,6 +2340,10 @@ export type DelSequenceData = {
 };
 
 export type DelSequenceErrors = {
+  /**
+   * Invalid filename
+   */
+  400: Status;
   /**
    * Something went wrong
    */
@@ -1689,6 +2374,10 @@ export type GetSequenceData = {
 };
 
 export type GetSequenceErrors = {
+  /**
+   * Invalid filename
+   */
+  400: Status;
   /**
    * The sequence wasn't found
    */
@@ -1769,6 +2458,294 @@ export type ListSequencesResponses = {
 
 export type ListSequencesResponse = ListSequencesResponses[keyof ListSequencesResponses];
 
+export type SearchSequencesData = {
+  body?: never;
+  path?: never;
+  query?: {
+    /**
+     * Text to find in the name or variables of the sequence
+     */
+    q?: string | null;
+    /**
+     * Only include sequences with this tag
+     */
+    tag?: string | null;
+    /**
+     * What to sort by
+     */
+    sort?: SequenceSort | null;
+    /**
+     * Sort in descending order
+     */
+    desc?: boolean | null;
+  };
+  url: "/api/sequences/search";
+};
+
+export type SearchSequencesErrors = {
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type SearchSequencesError = SearchSequencesErrors[keyof SearchSequencesErrors];
+
+export type SearchSequencesResponses = {
+  /**
+   * The matching sequences
+   */
+  200: Array<SequenceDetails>;
+};
+
+export type SearchSequencesResponse = SearchSequencesResponses[keyof SearchSequencesResponses];
+
+export type ListTagsData = {
+  body?: never;
+  path?: never;
+  query?: never;
+  url: "/api/sequences/tags";
+};
+
+export type ListTagsErrors = {
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type ListTagsError = ListTagsErrors[keyof ListTagsErrors];
+
+export type ListTagsResponses = {
+  /**
+   * The tags in use
+   */
+  200: Array<string>;
+};
+
+export type ListTagsResponse = ListTagsResponses[keyof ListTagsResponses];
+
+export type ConcatSequencesData = {
+  body: ConcatSequences;
+  path?: never;
+  query?: never;
+  url: "/api/sequences/concat";
+};
+
+export type ConcatSequencesErrors = {
+  /**
+   * The name is invalid or the sequences don't match
+   */
+  400: Status;
+  /**
+   * A sequence wasn't found
+   */
+  404: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type ConcatSequencesError = ConcatSequencesErrors[keyof ConcatSequencesErrors];
+
+export type ConcatSequencesResponses = {
+  /**
+   * The new sequence was created
+   */
+  200: Status;
+};
+
+export type ConcatSequencesResponse = ConcatSequencesResponses[keyof ConcatSequencesResponses];
+
+export type SetSequenceBrightnessData = {
+  body: Brightness;
+  path: {
+    /**
+     * The sequence to dim
+     */
+    filename: string;
+  };
+  query?: never;
+  url: "/api/sequence/{filename}/brightness";
+};
+
+export type SetSequenceBrightnessErrors = {
+  /**
+   * Brightness is more than 100%
+   */
+  400: Status;
+  /**
+   * The sequence wasn't found
+   */
+  404: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type SetSequenceBrightnessError =
+  SetSequenceBrightnessErrors[keyof SetSequenceBrightnessErrors];
+
+export type SetSequenceBrightnessResponses = {
+  /**
+   * The brightness was updated
+   */
+  200: Status;
+};
+
+export type SetSequenceBrightnessResponse =
+  SetSequenceBrightnessResponses[keyof SetSequenceBrightnessResponses];
+
+export type CopySequenceData = {
+  body: CopySequence;
+  path: {
+    /**
+     * The sequence to copy
+     */
+    filename: string;
+  };
+  query?: never;
+  url: "/api/sequence/{filename}/copy";
+};
+
+export type CopySequenceErrors = {
+  /**
+   * The new name is invalid or already used
+   */
+  400: Status;
+  /**
+   * The sequence wasn't found
+   */
+  404: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type CopySequenceError = CopySequenceErrors[keyof CopySequenceErrors];
+
+export type CopySequenceResponses = {
+  /**
+   * The sequence was copied
+   */
+  200: Status;
+};
+
+export type CopySequenceResponse = CopySequenceResponses[keyof CopySequenceResponses];
+
+export type RenameSequenceData = {
+  body: RenameSequence;
+  path: {
+    /**
+     * The sequence to rename
+     */
+    filename: string;
+  };
+  query?: never;
+  url: "/api/sequence/{filename}/rename";
+};
+
+export type RenameSequenceErrors = {
+  /**
+   * The new name is invalid or already used
+   */
+  400: Status;
+  /**
+   * The sequence wasn't found
+   */
+  404: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type RenameSequenceError = RenameSequenceErrors[keyof RenameSequenceErrors];
+
+export type RenameSequenceResponses = {
+  /**
+   * The sequence was renamed
+   */
+  200: Status;
+};
+
+export type RenameSequenceResponse = RenameSequenceResponses[keyof RenameSequenceResponses];
+
+export type SetSequenceTagsData = {
+  body: Array<string>;
+  path: {
+    /**
+     * The sequence to tag
+     */
+    filename: string;
+  };
+  query?: never;
+  url: "/api/sequence/{filename}/tags";
+};
+
+export type SetSequenceTagsErrors = {
+  /**
+   * The sequence wasn't found
+   */
+  404: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type SetSequenceTagsError = SetSequenceTagsErrors[keyof SetSequenceTagsErrors];
+
+export type SetSequenceTagsResponses = {
+  /**
+   * The tags were updated
+   */
+  200: Status;
+};
+
+export type SetSequenceTagsResponse = SetSequenceTagsResponses[keyof SetSequenceTagsResponses];
+
+export type TrimSequenceData = {
+  body: TrimSequence;
+  path: {
+    /**
+     * The sequence to trim
+     */
+    filename: string;
+  };
+  query?: never;
+  url: "/api/sequence/{filename}/trim";
+};
+
+export type TrimSequenceErrors = {
+  /**
+   * The name or time range is invalid
+   */
+  400: Status;
+  /**
+   * The sequence wasn't found
+   */
+  404: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type TrimSequenceError = TrimSequenceErrors[keyof TrimSequenceErrors];
+
+export type TrimSequenceResponses = {
+  /**
+   * The new sequence was created
+   */
+  200: Status;
+};
+
+export type TrimSequenceResponse = TrimSequenceResponses[keyof TrimSequenceResponses];
+
 export type SystemInfoData = {
   body?: never;
   path?: never;
@@ -1821,9 +2798,13 @@ export type FileUploadData = {
 
 export type FileUploadErrors = {
   /**
-   * Unrecognized file type
+   * Unrecognized file type or invalid filename
    */
   400: Status;
+  /**
+   * The upload is too large
+   */
+  413: Status;
   /**
    * Something went wrong
    */
@@ -1878,3 +2859,82 @@ export type FppCommandResponses = {
 };
 
 export type FppCommandResponse = FppCommandResponses[keyof FppCommandResponses];
+
+export type LoginData = {
+  body: Credentials;
+  path?: never;
+  query?: never;
+  url: "/api/auth/login";
+};
+
+export type LoginErrors = {
+  /**
+   * Wrong username or password
+   */
+  401: Status;
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type LoginError = LoginErrors[keyof LoginErrors];
+
+export type LoginResponses = {
+  /**
+   * Logged in, the session cookie is set
+   */
+  200: CurrentUser;
+};
+
+export type LoginResponse = LoginResponses[keyof LoginResponses];
+
+export type LogoutData = {
+  body?: never;
+  path?: never;
+  query?: never;
+  url: "/api/auth/logout";
+};
+
+export type LogoutErrors = {
+  /**
+   * Something went wrong
+   */
+  500: Status;
+};
+
+export type LogoutError = LogoutErrors[keyof LogoutErrors];
+
+export type LogoutResponses = {
+  /**
+   * Logged out
+   */
+  200: Status;
+};
+
+export type LogoutResponse = LogoutResponses[keyof LogoutResponses];
+
+export type CurrentUserData = {
+  body?: never;
+  path?: never;
+  query?: never;
+  url: "/api/auth/me";
+};
+
+export type CurrentUserErrors = {
+  /**
+   * Not logged in
+   */
+  401: Status;
+};
+
+export type CurrentUserError = CurrentUserErrors[keyof CurrentUserErrors];
+
+export type CurrentUserResponses = {
+  /**
+   * The current user
+   */
+  200: CurrentUser;
+};
+
+export type CurrentUserResponse = CurrentUserResponses[keyof CurrentUserResponses];
@@ -43,6 +43,7 @@ export function isPlaying(status: PlayerStatus): boolean | undefined {
     case "playlist":
     case "sequence":
     case "testing":
+    case "bridge":
       return true;
   }
   return false;
@@ -50,7 +51,7 @@ export function isPlaying(status: PlayerStatus): boolean | undefined {
 
 export async function updateStatus() {
   try {
-    playerStatus.set(getPlayerStatus((await getStatus()).data));
+    playerStatus.set(getPlayerStatus((await getStatus()).data?.status));
   } catch (_err) {
     playerStatus.set("unknown");
   }